@@ -0,0 +1,278 @@
+//! # Candump Module
+//!
+//! The `candump` module renders raw CAN frames in the compact `702#7F`
+//! notation of the Linux can-utils and parses lines of `candump` logs.
+
+use std::fmt::Write;
+use std::string::String;
+
+use crate::raw::*;
+use core::fmt;
+
+/// Errors reported while parsing or writing `candump` lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandumpError {
+    /// The line matches none of the supported formats.
+    InvalidFormat,
+    /// The CAN identifier is not a valid hexadecimal 11-bit or 29-bit identifier.
+    InvalidId,
+    /// The data length is missing, above 8 or does not match the data.
+    InvalidLength,
+    /// A data byte is not a two-digit hexadecimal number.
+    InvalidData,
+}
+
+impl fmt::Display for CandumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandumpError::InvalidFormat => write!(f, "invalid candump line"),
+            CandumpError::InvalidId => write!(f, "invalid CAN identifier"),
+            CandumpError::InvalidLength => write!(f, "invalid data length"),
+            CandumpError::InvalidData => write!(f, "invalid data byte"),
+        }
+    }
+}
+
+impl core::error::Error for CandumpError {}
+
+impl CANFrame {
+    /// Renders the frame as `<id>#<data>`, e.g. `702#7F`.
+    ///
+    /// Extended identifiers are written with 8 digits, standard ones with 3.
+    /// Remote frames are written as `<id>#R<len>`. Frames that
+    /// [`CANFrame::from_candump_line`] would reject, i.e. with an identifier
+    /// out of range or more than 8 bytes, are rejected as well.
+    pub fn to_hex_string(&self) -> Result<String, CandumpError> {
+        let mask = if self.is_extended {
+            CAN_EFF_MASK
+        } else {
+            CAN_SFF_MASK
+        };
+        if self.can_cobid > mask {
+            return Err(CandumpError::InvalidId);
+        }
+        if self.can_len > 8 {
+            return Err(CandumpError::InvalidLength);
+        }
+
+        let mut s = String::new();
+
+        // writing into a String cannot fail
+        if self.is_extended {
+            let _ = write!(s, "{:08X}#", self.can_cobid);
+        } else {
+            let _ = write!(s, "{:03X}#", self.can_cobid);
+        }
+
+        if self.is_rtr {
+            s.push('R');
+            if self.can_len > 0 {
+                let _ = write!(s, "{}", self.can_len);
+            }
+        } else {
+            for x in &self.can_data[..self.can_len] {
+                let _ = write!(s, "{:02X}", x);
+            }
+        }
+
+        Ok(s)
+    }
+
+    /// Parses a frame from a `candump` line.
+    ///
+    /// Both the compact `702#7F` form, optionally preceded by a timestamp and
+    /// interface as written by `candump -L`, and the default `can0  702   [1]  7F`
+    /// form are accepted.
+    pub fn from_candump_line(line: &str) -> Result<CANFrame, CandumpError> {
+        if let Some(compact) = line.split_whitespace().find(|t| t.contains('#')) {
+            return parse_compact(compact);
+        }
+
+        let mut tokens = line.split_whitespace();
+
+        let _interface = tokens.next().ok_or(CandumpError::InvalidFormat)?;
+        let id = tokens.next().ok_or(CandumpError::InvalidFormat)?;
+        let len = tokens
+            .next()
+            .and_then(|t| t.strip_prefix('['))
+            .and_then(|t| t.strip_suffix(']'))
+            .ok_or(CandumpError::InvalidFormat)?;
+
+        let mut frame = CANFrame::default();
+        (frame.can_cobid, frame.is_extended) = parse_id(id)?;
+        frame.can_len = len.parse().map_err(|_| CandumpError::InvalidLength)?;
+
+        if frame.can_len > 8 {
+            return Err(CandumpError::InvalidLength);
+        }
+
+        let rest: std::vec::Vec<&str> = tokens.collect();
+        if rest == ["remote", "request"] {
+            frame.is_rtr = true;
+            return Ok(frame);
+        }
+
+        if rest.len() != frame.can_len {
+            return Err(CandumpError::InvalidLength);
+        }
+
+        for (x, token) in frame.can_data.iter_mut().zip(rest) {
+            *x = parse_byte(token)?;
+        }
+
+        Ok(frame)
+    }
+}
+
+/// Parses the compact `<id>#<data>` or `<id>#R<len>` form.
+fn parse_compact(s: &str) -> Result<CANFrame, CandumpError> {
+    let (id, data) = s.split_once('#').ok_or(CandumpError::InvalidFormat)?;
+
+    let mut frame = CANFrame::default();
+    (frame.can_cobid, frame.is_extended) = parse_id(id)?;
+
+    if let Some(len) = data.strip_prefix('R') {
+        frame.is_rtr = true;
+        frame.can_len = match len {
+            "" => 0,
+            _ => len.parse().map_err(|_| CandumpError::InvalidLength)?,
+        };
+        if frame.can_len > 8 {
+            return Err(CandumpError::InvalidLength);
+        }
+        return Ok(frame);
+    }
+
+    // can-utils allows dots between the data bytes
+    let digits: String = data.chars().filter(|c| *c != '.').collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(CandumpError::InvalidData);
+    }
+    if digits.len() > 16 {
+        return Err(CandumpError::InvalidLength);
+    }
+
+    frame.can_len = digits.len() / 2;
+    for (i, x) in frame.can_data[..frame.can_len].iter_mut().enumerate() {
+        *x = parse_byte(&digits[2 * i..2 * i + 2])?;
+    }
+
+    Ok(frame)
+}
+
+/// Parses a hexadecimal identifier, 8 digits denoting an extended one.
+fn parse_id(s: &str) -> Result<(u32, bool), CandumpError> {
+    let id = u32::from_str_radix(s, 16).map_err(|_| CandumpError::InvalidId)?;
+
+    match s.len() {
+        1..=3 if id <= CAN_SFF_MASK => Ok((id, false)),
+        8 if id <= CAN_EFF_MASK => Ok((id, true)),
+        _ => Err(CandumpError::InvalidId),
+    }
+}
+
+/// Parses a two-digit hexadecimal byte.
+fn parse_byte(s: &str) -> Result<u8, CandumpError> {
+    if s.len() != 2 {
+        return Err(CandumpError::InvalidData);
+    }
+
+    u8::from_str_radix(s, 16).map_err(|_| CandumpError::InvalidData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candump_compact() {
+        let frame = CANFrame::from_candump_line("702#7F").unwrap();
+
+        assert_eq!(frame.can_cobid, 0x702);
+        assert_eq!(frame.can_len, 1);
+        assert_eq!(frame.can_data[0], 0x7f);
+        assert_eq!(frame.to_hex_string().unwrap(), "702#7F");
+
+        let frame =
+            CANFrame::from_candump_line("(1700000000.123456) can0 12345678#0102.03").unwrap();
+
+        assert!(frame.is_extended);
+        assert_eq!(frame.can_cobid, 0x1234_5678);
+        assert_eq!(&frame.can_data[..3], &[0x01, 0x02, 0x03]);
+        assert_eq!(frame.to_hex_string().unwrap(), "12345678#010203");
+
+        let frame = CANFrame::from_candump_line("705#R1").unwrap();
+
+        assert!(frame.is_rtr);
+        assert_eq!(frame.can_len, 1);
+        assert_eq!(frame.to_hex_string().unwrap(), "705#R1");
+
+        let frame = CANFrame::from_candump_line("080#").unwrap();
+
+        assert_eq!(frame.can_len, 0);
+        assert_eq!(frame.to_hex_string().unwrap(), "080#");
+    }
+
+    #[test]
+    fn test_candump_default_format() {
+        let frame = CANFrame::from_candump_line("  can0  702   [1]  7F").unwrap();
+
+        assert_eq!(frame.can_cobid, 0x702);
+        assert_eq!(frame.can_len, 1);
+        assert_eq!(frame.can_data[0], 0x7f);
+
+        let frame =
+            CANFrame::from_candump_line("can0  581   [8]  43 00 10 00 92 01 00 00").unwrap();
+
+        assert_eq!(frame.to_hex_string().unwrap(), "581#4300100092010000");
+        assert_eq!(
+            CANFrame::from_candump_line(&frame.to_hex_string().unwrap()),
+            Ok(frame)
+        );
+
+        let frame = CANFrame::from_candump_line("can0  705   [1]  remote request").unwrap();
+
+        assert!(frame.is_rtr);
+        assert_eq!(frame.can_len, 1);
+    }
+
+    #[test]
+    fn test_candump_invalid() {
+        let lines = [
+            ("", CandumpError::InvalidFormat),
+            ("can0 702 7F", CandumpError::InvalidFormat),
+            ("can0 702 [2] 7F", CandumpError::InvalidLength),
+            (
+                "can0 702 [9] 00 00 00 00 00 00 00 00 00",
+                CandumpError::InvalidLength,
+            ),
+            ("can0 702 [1] 7G", CandumpError::InvalidData),
+            ("800#00", CandumpError::InvalidId),
+            ("7020#00", CandumpError::InvalidId),
+            ("702#7", CandumpError::InvalidData),
+            ("702#000000000000000000", CandumpError::InvalidLength),
+            ("702#R9", CandumpError::InvalidLength),
+        ];
+
+        for (line, e) in lines {
+            assert_eq!(CANFrame::from_candump_line(line), Err(e), "{}", line);
+        }
+
+        // frames that could not be parsed back are not written either
+        let mut frame = CANFrame {
+            can_cobid: 0x800,
+            ..Default::default()
+        };
+        assert_eq!(frame.to_hex_string(), Err(CandumpError::InvalidId));
+
+        frame.is_extended = true;
+        assert_eq!(frame.to_hex_string().unwrap(), "00000800#");
+
+        frame.can_cobid = CAN_EFF_MASK + 1;
+        assert_eq!(frame.to_hex_string(), Err(CandumpError::InvalidId));
+
+        frame.can_cobid = 0x702;
+        frame.can_len = 9;
+        assert_eq!(frame.to_hex_string(), Err(CandumpError::InvalidLength));
+    }
+}
@@ -0,0 +1,133 @@
+//! # Embedded Module
+//!
+//! The `embedded` module bridges raw CAN frames with the `embedded-can`
+//! traits implemented by microcontroller HALs.
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+use crate::raw::*;
+
+impl Frame for CANFrame {
+    /// Creates a data frame, returning `None` if `data` is longer than 8 bytes.
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+
+        let frame = CANFrame::builder().data(data).build();
+        Some(with_id(frame, id.into()))
+    }
+
+    /// Creates a remote frame, returning `None` if `dlc` is above 8.
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+
+        let frame = CANFrame {
+            is_rtr: true,
+            can_len: dlc,
+            ..Default::default()
+        };
+        Some(with_id(frame, id.into()))
+    }
+
+    fn is_extended(&self) -> bool {
+        self.is_extended
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.is_rtr
+    }
+
+    /// Returns the identifier, truncated to 11 or 29 bits.
+    fn id(&self) -> Id {
+        if self.is_extended {
+            ExtendedId::new(self.can_cobid & CAN_EFF_MASK)
+                .map(Id::Extended)
+                .unwrap_or(Id::Extended(ExtendedId::ZERO))
+        } else {
+            StandardId::new((self.can_cobid & CAN_SFF_MASK) as u16)
+                .map(Id::Standard)
+                .unwrap_or(Id::Standard(StandardId::ZERO))
+        }
+    }
+
+    fn dlc(&self) -> usize {
+        self.can_len
+    }
+
+    /// Returns the valid data bytes, which are empty for remote frames.
+    fn data(&self) -> &[u8] {
+        if self.is_rtr {
+            &[]
+        } else {
+            &self.can_data[..self.can_len.min(8)]
+        }
+    }
+}
+
+fn with_id(mut frame: CANFrame, id: Id) -> CANFrame {
+    match id {
+        Id::Standard(id) => {
+            frame.can_cobid = id.as_raw().into();
+            frame.is_extended = false;
+        }
+        Id::Extended(id) => {
+            frame.can_cobid = id.as_raw();
+            frame.is_extended = true;
+        }
+    }
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_can_data_frame() {
+        let id = StandardId::new(0x181).unwrap();
+        let frame = <CANFrame as Frame>::new(id, &[1, 2, 3]).unwrap();
+
+        assert_eq!(frame.can_cobid, 0x181);
+        assert!(frame.is_standard());
+        assert!(frame.is_data_frame());
+        assert_eq!(frame.id(), Id::Standard(id));
+        assert_eq!(frame.dlc(), 3);
+        assert_eq!(Frame::data(&frame), &[1, 2, 3]);
+
+        assert!(<CANFrame as Frame>::new(id, &[0; 9]).is_none());
+    }
+
+    #[test]
+    fn test_embedded_can_remote_frame() {
+        let frame = CANFrame::new_remote(StandardId::new(0x705).unwrap(), 1).unwrap();
+
+        assert!(frame.is_rtr);
+        assert!(frame.is_remote_frame());
+        assert_eq!(frame.dlc(), 1);
+        assert!(Frame::data(&frame).is_empty());
+
+        assert!(CANFrame::new_remote(StandardId::ZERO, 9).is_none());
+    }
+
+    #[test]
+    fn test_embedded_can_id_range() {
+        let frame = <CANFrame as Frame>::new(StandardId::MAX, &[]).unwrap();
+
+        assert_eq!(frame.can_cobid, 0x7ff);
+        assert_eq!(frame.id(), Id::Standard(StandardId::MAX));
+
+        let frame = <CANFrame as Frame>::new(ExtendedId::MAX, &[]).unwrap();
+
+        assert!(frame.is_extended);
+        assert_eq!(frame.can_cobid, 0x1fff_ffff);
+        assert_eq!(frame.id(), Id::Extended(ExtendedId::MAX));
+
+        // bits beyond the standard id are dropped
+        let frame = CANFrame::builder().cobid(0x800).build();
+
+        assert_eq!(frame.id(), Id::Standard(StandardId::ZERO));
+    }
+}
@@ -0,0 +1,535 @@
+//! # Emergency Module
+//!
+//! The `emcy` module provides CANOpen emergency (EMCY) messages and a
+//! producer raising them for the local node.
+
+use crate::raw::*;
+use core::fmt;
+
+/// Base COB-ID of emergency messages, the node ID is added to it.
+pub const EMCY_COBID: u32 = 0x080;
+
+/// Error code of the "error reset / no error" message.
+pub const EMCY_NO_ERROR: u16 = 0x0000;
+
+/// Generic error bit of the error register, set while any error is active.
+pub const ERROR_REGISTER_GENERIC: u8 = 0x01;
+
+/// Errors reported by the emergency producer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EmcyError {
+    /// The error code cannot be raised, e.g. the "no error" code.
+    InvalidErrorCode(u16),
+    /// More than 5 bytes of manufacturer specific data were given.
+    ManufacturerDataTooLong(usize),
+    /// The table of active errors is full.
+    TooManyErrors,
+    /// The queue of messages held back by the inhibit time is full.
+    QueueFull,
+    /// The error history only accepts writing zero to sub-index 0.
+    InvalidHistoryWrite {
+        /// Sub-index written to.
+        sub: u8,
+        /// Value written.
+        value: u32,
+    },
+}
+
+impl fmt::Display for EmcyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmcyError::InvalidErrorCode(code) => write!(f, "invalid error code {:#06x}", code),
+            EmcyError::ManufacturerDataTooLong(len) => {
+                write!(f, "manufacturer data too long: {} bytes", len)
+            }
+            EmcyError::TooManyErrors => write!(f, "too many active errors"),
+            EmcyError::QueueFull => write!(f, "emergency message queue full"),
+            EmcyError::InvalidHistoryWrite { sub, value } => {
+                write!(
+                    f,
+                    "invalid error history write {:#x} to sub-index {}",
+                    value, sub
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for EmcyError {}
+
+/// An emergency message.
+///
+/// # Fields
+///
+/// * `error_code` - The emergency error code.
+/// * `error_register` - The error register (object 0x1001) at the time of the error.
+/// * `manufacturer` - Manufacturer specific error data.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmcyMessage {
+    /// The emergency error code.
+    pub error_code: u16,
+
+    /// The error register of the node.
+    pub error_register: u8,
+
+    /// Manufacturer specific error data.
+    pub manufacturer: [u8; 5],
+}
+
+impl From<EmcyMessage> for [u8; 8] {
+    fn from(msg: EmcyMessage) -> [u8; 8] {
+        let mut data = [0; 8];
+        data[0..2].copy_from_slice(&msg.error_code.to_le_bytes());
+        data[2] = msg.error_register;
+        data[3..8].copy_from_slice(&msg.manufacturer);
+        data
+    }
+}
+
+impl From<[u8; 8]> for EmcyMessage {
+    fn from(data: [u8; 8]) -> Self {
+        let mut manufacturer = [0; 5];
+        manufacturer.copy_from_slice(&data[3..8]);
+
+        Self {
+            error_code: u16::from_le_bytes([data[0], data[1]]),
+            error_register: data[2],
+            manufacturer,
+        }
+    }
+}
+
+/// An error currently raised by the producer.
+#[derive(Debug, Clone, Copy)]
+struct ActiveError {
+    code: u16,
+    register: u8,
+}
+
+/// Producer of the emergency messages of the local node.
+///
+/// The producer keeps track of up to `N` active errors and derives the error
+/// register (object 0x1001) from them. Clearing the last active error emits
+/// the "error reset / no error" message.
+///
+/// An inhibit time (object 0x1015, in multiples of 100µs) enforces a minimum
+/// gap between two messages. Messages produced too early are queued, up to
+/// `Q` of them, and returned by [`EmcyProducer::tick`] in order, one per
+/// inhibit time. Raising or clearing an error while the queue is full fails.
+/// Time is ticked in milliseconds like the other machines and tracked in
+/// units of 100µs.
+pub struct EmcyProducer<const N: usize, const Q: usize = 4> {
+    node: u8,
+    inhibit: u16,
+    // in multiples of 100µs
+    since_last: u32,
+    active: [Option<ActiveError>; N],
+    queue: [Option<CANFrame>; Q],
+    head: usize,
+    queued: usize,
+}
+
+impl<const N: usize, const Q: usize> EmcyProducer<N, Q> {
+    /// Creates a producer for `node` with an inhibit time in multiples of 100µs.
+    pub fn new(node: u8, inhibit: u16) -> Self {
+        Self {
+            node,
+            inhibit,
+            since_last: u32::MAX,
+            active: [None; N],
+            queue: [None; Q],
+            head: 0,
+            queued: 0,
+        }
+    }
+
+    /// Returns the current error register value.
+    pub fn error_register(&self) -> u8 {
+        self.active
+            .iter()
+            .flatten()
+            .fold(0, |reg, e| reg | e.register | ERROR_REGISTER_GENERIC)
+    }
+
+    /// Returns `true` if the error code is currently active.
+    pub fn is_active(&self, code: u16) -> bool {
+        self.active.iter().flatten().any(|e| e.code == code)
+    }
+
+    /// Changes the inhibit time, in multiples of 100µs.
+    pub fn set_inhibit(&mut self, inhibit: u16) {
+        self.inhibit = inhibit;
+    }
+
+    /// Raises an error, returning the emergency frame to send now.
+    ///
+    /// `register` holds the error register bits caused by the error, the
+    /// generic error bit is always set while an error is active.
+    pub fn raise(
+        &mut self,
+        code: u16,
+        register: u8,
+        manufacturer: &[u8],
+    ) -> Result<Option<CANFrame>, EmcyError> {
+        if code == EMCY_NO_ERROR {
+            return Err(EmcyError::InvalidErrorCode(code));
+        }
+
+        if manufacturer.len() > 5 {
+            return Err(EmcyError::ManufacturerDataTooLong(manufacturer.len()));
+        }
+
+        if self.queue_full() {
+            return Err(EmcyError::QueueFull);
+        }
+
+        let error = ActiveError { code, register };
+        match self
+            .active
+            .iter()
+            .position(|e| e.map(|e| e.code) == Some(code))
+        {
+            Some(i) => self.active[i] = Some(error),
+            None => {
+                let slot = self
+                    .active
+                    .iter_mut()
+                    .find(|e| e.is_none())
+                    .ok_or(EmcyError::TooManyErrors)?;
+                *slot = Some(error);
+            }
+        }
+
+        let mut msg = EmcyMessage {
+            error_code: code,
+            error_register: self.error_register(),
+            ..Default::default()
+        };
+        msg.manufacturer[..manufacturer.len()].copy_from_slice(manufacturer);
+
+        Ok(self.emit(msg))
+    }
+
+    /// Clears an error, returning the frame to send now.
+    ///
+    /// Only clearing the last active error produces a message.
+    pub fn clear(&mut self, code: u16) -> Result<Option<CANFrame>, EmcyError> {
+        let Some(i) = self
+            .active
+            .iter()
+            .position(|e| e.map(|e| e.code) == Some(code))
+        else {
+            return Ok(None);
+        };
+
+        let last = self.active.iter().flatten().count() == 1;
+        if last && self.queue_full() {
+            return Err(EmcyError::QueueFull);
+        }
+
+        self.active[i] = None;
+
+        if !last {
+            return Ok(None);
+        }
+
+        Ok(self.emit(EmcyMessage::default()))
+    }
+
+    /// Advances time by `ms` milliseconds, returning the oldest held back frame once it may be sent.
+    pub fn tick(&mut self, ms: u32) -> Option<CANFrame> {
+        self.since_last = self.since_last.saturating_add(ms.saturating_mul(10));
+
+        if self.queued == 0 || self.inhibited() {
+            return None;
+        }
+
+        let frame = self.queue[self.head].take();
+        self.head = (self.head + 1) % Q;
+        self.queued -= 1;
+        self.since_last = 0;
+        frame
+    }
+
+    fn inhibited(&self) -> bool {
+        self.since_last < u32::from(self.inhibit)
+    }
+
+    /// Returns `true` if the next message would be held back but cannot be queued.
+    fn queue_full(&self) -> bool {
+        (self.inhibited() || self.queued > 0) && self.queued == Q
+    }
+
+    fn emit(&mut self, msg: EmcyMessage) -> Option<CANFrame> {
+        let frame = CANFrame {
+            can_cobid: EMCY_COBID + u32::from(self.node),
+            can_len: 8,
+            can_data: msg.into(),
+            ..Default::default()
+        };
+
+        // queued messages go first, so a new one waits behind them
+        if self.inhibited() || self.queued > 0 {
+            self.queue[(self.head + self.queued) % Q] = Some(frame);
+            self.queued += 1;
+            None
+        } else {
+            self.since_last = 0;
+            Some(frame)
+        }
+    }
+}
+
+/// The pre-defined error field (object 0x1003) of a node.
+///
+/// Keeps the error codes of the last `N` emergency messages, newest first.
+/// Sub-index 0 holds the number of recorded errors and sub-indices 1..=N
+/// the errors themselves, sub-index 1 being the newest. When the history is
+/// full the oldest error is dropped. Writing 0 to sub-index 0 clears it.
+pub struct ErrorHistory<const N: usize> {
+    entries: [u32; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for ErrorHistory<N> {
+    fn default() -> Self {
+        Self {
+            entries: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> ErrorHistory<N> {
+    /// Records the error code of an emergency message.
+    ///
+    /// Error reset messages are not errors and are not recorded.
+    pub fn record(&mut self, msg: &EmcyMessage) {
+        if msg.error_code == EMCY_NO_ERROR || N == 0 {
+            return;
+        }
+
+        self.entries[self.head] = msg.error_code.into();
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Returns the number of recorded errors.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no errors are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forgets all recorded errors.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Reads a sub-index of the error field.
+    ///
+    /// Sub-index 0 returns the number of errors, sub-index `i` the `i`-th newest error.
+    pub fn get(&self, sub: u8) -> Option<u32> {
+        let sub = usize::from(sub);
+
+        if sub == 0 {
+            Some(self.len as u32)
+        } else if sub <= self.len {
+            Some(self.entries[(self.head + N - sub) % N])
+        } else {
+            None
+        }
+    }
+
+    /// Writes a sub-index of the error field.
+    ///
+    /// Only writing 0 to sub-index 0, which clears the history, is allowed.
+    pub fn set(&mut self, sub: u8, value: u32) -> Result<(), EmcyError> {
+        if sub == 0 && value == 0 {
+            self.clear();
+            Ok(())
+        } else {
+            Err(EmcyError::InvalidHistoryWrite { sub, value })
+        }
+    }
+
+    /// Iterates over the recorded errors, newest first.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=self.len).map(move |i| self.entries[(self.head + N - i) % N])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emcy_message_encode_decode() {
+        let msg = EmcyMessage {
+            error_code: 0x8130,
+            error_register: 0x11,
+            manufacturer: [1, 2, 3, 4, 5],
+        };
+        let data: [u8; 8] = msg.into();
+
+        assert_eq!(data, [0x30, 0x81, 0x11, 1, 2, 3, 4, 5]);
+        assert_eq!(EmcyMessage::from(data), msg);
+    }
+
+    #[test]
+    fn test_emcy_producer_raise_clear() {
+        let mut producer = EmcyProducer::<4>::new(3, 0);
+
+        let frame = producer.raise(0x8130, 0x10, &[0xaa]).unwrap().unwrap();
+
+        assert_eq!(frame.can_cobid, 0x083);
+        assert_eq!(frame.can_data, [0x30, 0x81, 0x11, 0xaa, 0, 0, 0, 0]);
+
+        let frame = producer.raise(0x3210, 0x04, &[]).unwrap().unwrap();
+
+        assert_eq!(frame.can_data[0..3], [0x10, 0x32, 0x15]);
+        assert_eq!(producer.error_register(), 0x15);
+
+        // another error is still active
+        assert_eq!(producer.clear(0x8130), Ok(None));
+        assert_eq!(producer.error_register(), 0x05);
+        assert!(!producer.is_active(0x8130));
+        assert!(producer.is_active(0x3210));
+
+        let frame = producer.clear(0x3210).unwrap().unwrap();
+
+        assert_eq!(frame.can_data, [0; 8]);
+        assert_eq!(producer.error_register(), 0);
+
+        // nothing left to clear
+        assert_eq!(producer.clear(0x3210), Ok(None));
+    }
+
+    #[test]
+    fn test_emcy_producer_invalid() {
+        let mut producer = EmcyProducer::<1>::new(3, 0);
+
+        assert_eq!(
+            producer.raise(EMCY_NO_ERROR, 0, &[]),
+            Err(EmcyError::InvalidErrorCode(0))
+        );
+        assert_eq!(
+            producer.raise(0x1000, 0, &[0; 6]),
+            Err(EmcyError::ManufacturerDataTooLong(6))
+        );
+
+        producer.raise(0x1000, 0, &[]).unwrap();
+
+        assert_eq!(
+            producer.raise(0x2000, 0, &[]),
+            Err(EmcyError::TooManyErrors)
+        );
+    }
+
+    #[test]
+    fn test_emcy_producer_inhibit() {
+        // 10ms inhibit time
+        let mut producer = EmcyProducer::<4>::new(3, 100);
+
+        assert!(producer.raise(0x8130, 0, &[]).unwrap().is_some());
+
+        producer.tick(5);
+        assert_eq!(producer.raise(0x3210, 0, &[]).unwrap(), None);
+        assert_eq!(producer.tick(4), None);
+
+        let frame = producer.tick(1).unwrap();
+
+        assert_eq!(frame.can_data[0..2], [0x10, 0x32]);
+        assert_eq!(producer.tick(100), None);
+    }
+
+    #[test]
+    fn test_emcy_producer_queue() {
+        // 10ms inhibit time, two messages may be held back
+        let mut producer = EmcyProducer::<4, 2>::new(3, 100);
+
+        assert!(producer.raise(0x8130, 0, &[]).unwrap().is_some());
+        assert_eq!(producer.raise(0x3210, 0, &[]).unwrap(), None);
+        assert_eq!(producer.raise(0x4210, 0, &[]).unwrap(), None);
+
+        assert_eq!(producer.raise(0x5000, 0, &[]), Err(EmcyError::QueueFull));
+        assert!(!producer.is_active(0x5000));
+
+        // held back messages are sent in order, one per inhibit time
+        let frame = producer.tick(10).unwrap();
+        assert_eq!(frame.can_data[0..2], [0x10, 0x32]);
+        assert_eq!(producer.tick(9), None);
+
+        let frame = producer.tick(1).unwrap();
+        assert_eq!(frame.can_data[0..2], [0x10, 0x42]);
+        assert_eq!(producer.tick(100), None);
+    }
+
+    fn emcy(error_code: u16) -> EmcyMessage {
+        EmcyMessage {
+            error_code,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_error_history_overflow() {
+        let mut history = ErrorHistory::<3>::default();
+
+        assert!(history.is_empty());
+        assert_eq!(history.get(0), Some(0));
+        assert_eq!(history.get(1), None);
+
+        for code in [0x1000, 0x2000, 0x3000, 0x4000] {
+            history.record(&emcy(code));
+        }
+
+        // the oldest error was dropped
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.get(0), Some(3));
+        assert_eq!(history.get(1), Some(0x4000));
+        assert_eq!(history.get(2), Some(0x3000));
+        assert_eq!(history.get(3), Some(0x2000));
+        assert_eq!(history.get(4), None);
+        assert!(history.iter().eq([0x4000, 0x3000, 0x2000]));
+
+        // error reset messages are not recorded
+        history.record(&emcy(EMCY_NO_ERROR));
+        assert_eq!(history.get(1), Some(0x4000));
+    }
+
+    #[test]
+    fn test_error_history_clear_on_write_zero() {
+        let mut history = ErrorHistory::<4>::default();
+
+        history.record(&emcy(0x8130));
+        history.record(&emcy(0x3210));
+
+        assert_eq!(
+            history.set(0, 1),
+            Err(EmcyError::InvalidHistoryWrite { sub: 0, value: 1 })
+        );
+        assert_eq!(
+            history.set(1, 0),
+            Err(EmcyError::InvalidHistoryWrite { sub: 1, value: 0 })
+        );
+        assert_eq!(history.len(), 2);
+
+        assert_eq!(history.set(0, 0), Ok(()));
+        assert!(history.is_empty());
+        assert_eq!(history.get(1), None);
+
+        history.record(&emcy(0x5000));
+        assert!(history.iter().eq([0x5000]));
+    }
+}
@@ -0,0 +1,157 @@
+//! # Error Module
+//!
+//! The `error` module provides the crate-wide error type wrapping the errors
+//! of the individual modules.
+
+use core::fmt;
+
+use crate::emcy::EmcyError;
+use crate::heartbeat::HeartbeatError;
+use crate::lss::LssError;
+use crate::mpdo::MpdoError;
+use crate::nmt::NmtError;
+use crate::pdo::PdoError;
+use crate::raw::FrameError;
+use crate::sync::SyncError;
+use crate::time::TimeError;
+
+/// Errors of all modules of the crate.
+///
+/// Every module error converts into it with `?`, so applications can use a
+/// single error type, e.g. with a `From<funcan_rs::Error>` bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Raw CAN frame error.
+    Frame(FrameError),
+    /// NMT error.
+    Nmt(NmtError),
+    /// TIME protocol error.
+    Time(TimeError),
+    /// Layer setting services error.
+    Lss(LssError),
+    /// Emergency producer error.
+    Emcy(EmcyError),
+    /// Multiplexed PDO error.
+    Mpdo(MpdoError),
+    /// Heartbeat consumer error.
+    Heartbeat(HeartbeatError),
+    /// PDO error.
+    Pdo(PdoError),
+    /// SYNC protocol error.
+    Sync(SyncError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Frame(e) => write!(f, "frame error: {}", e),
+            Error::Nmt(e) => write!(f, "NMT error: {}", e),
+            Error::Time(e) => write!(f, "TIME error: {}", e),
+            Error::Lss(e) => write!(f, "LSS error: {}", e),
+            Error::Emcy(e) => write!(f, "EMCY error: {}", e),
+            Error::Mpdo(e) => write!(f, "MPDO error: {}", e),
+            Error::Heartbeat(e) => write!(f, "heartbeat error: {}", e),
+            Error::Pdo(e) => write!(f, "PDO error: {}", e),
+            Error::Sync(e) => write!(f, "SYNC error: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Frame(e) => Some(e),
+            Error::Nmt(e) => Some(e),
+            Error::Time(e) => Some(e),
+            Error::Lss(e) => Some(e),
+            Error::Emcy(e) => Some(e),
+            Error::Mpdo(e) => Some(e),
+            Error::Heartbeat(e) => Some(e),
+            Error::Pdo(e) => Some(e),
+            Error::Sync(e) => Some(e),
+        }
+    }
+}
+
+impl From<FrameError> for Error {
+    fn from(e: FrameError) -> Self {
+        Error::Frame(e)
+    }
+}
+
+impl From<NmtError> for Error {
+    fn from(e: NmtError) -> Self {
+        Error::Nmt(e)
+    }
+}
+
+impl From<TimeError> for Error {
+    fn from(e: TimeError) -> Self {
+        Error::Time(e)
+    }
+}
+
+impl From<LssError> for Error {
+    fn from(e: LssError) -> Self {
+        Error::Lss(e)
+    }
+}
+
+impl From<EmcyError> for Error {
+    fn from(e: EmcyError) -> Self {
+        Error::Emcy(e)
+    }
+}
+
+impl From<MpdoError> for Error {
+    fn from(e: MpdoError) -> Self {
+        Error::Mpdo(e)
+    }
+}
+
+impl From<HeartbeatError> for Error {
+    fn from(e: HeartbeatError) -> Self {
+        Error::Heartbeat(e)
+    }
+}
+
+impl From<PdoError> for Error {
+    fn from(e: PdoError) -> Self {
+        Error::Pdo(e)
+    }
+}
+
+impl From<SyncError> for Error {
+    fn from(e: SyncError) -> Self {
+        Error::Sync(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    fn decode_state(x: u8) -> Result<crate::nmt::NmtState, Error> {
+        Ok(crate::nmt::NmtState::try_from(x)?)
+    }
+
+    #[test]
+    fn test_error_conversion() {
+        assert_eq!(
+            decode_state(0x02),
+            Err(Error::Nmt(NmtError::InvalidState(2)))
+        );
+
+        let e = Error::from(FrameError::BufferTooSmall { needed: 16, got: 4 });
+
+        assert_eq!(
+            e.to_string(),
+            "frame error: buffer too small: needed 16 bytes, got 4"
+        );
+        assert!(core::error::Error::source(&e).is_some());
+    }
+}
@@ -0,0 +1,868 @@
+//! # Heartbeat Module
+//!
+//! The `heartbeat` module provides the error control services of CANOpen:
+//! the heartbeat producer, which periodically publishes the local NMT state,
+//! node guarding, which lets a master poll the NMT state of a node, and the
+//! heartbeat consumer monitoring the heartbeats of other nodes.
+
+use crate::machine::*;
+use crate::nmt::*;
+use crate::raw::*;
+use core::fmt;
+
+/// Base COB-ID of the error control services, the node ID is added to it.
+pub const ERROR_CONTROL_COBID: u32 = 0x700;
+
+/// Toggle bit of a node guarding response.
+const TOGGLE_BIT: u8 = 0x80;
+
+/// Errors reported while configuring the heartbeat consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HeartbeatError {
+    /// The sub-index does not address a writable consumer entry.
+    InvalidSubIndex(u8),
+    /// The node ID is above 127.
+    InvalidNodeId(u8),
+    /// The reserved bits 24..31 of a consumer entry are set.
+    InvalidEntry(u32),
+    /// Another enabled entry already monitors the node.
+    DuplicateNode(u8),
+}
+
+impl fmt::Display for HeartbeatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeartbeatError::InvalidSubIndex(sub) => {
+                write!(f, "invalid consumer heartbeat sub-index {}", sub)
+            }
+            HeartbeatError::InvalidNodeId(node) => write!(f, "invalid node ID {}", node),
+            HeartbeatError::InvalidEntry(value) => {
+                write!(f, "reserved bits set in consumer entry {:#010x}", value)
+            }
+            HeartbeatError::DuplicateNode(node) => {
+                write!(f, "node {} is already monitored", node)
+            }
+        }
+    }
+}
+
+impl core::error::Error for HeartbeatError {}
+
+/// Builds the error control frame reporting `state` for `node`.
+fn state_frame(node: u8, state: NmtState) -> CANFrame {
+    CANFrame {
+        can_cobid: ERROR_CONTROL_COBID + u32::from(node),
+        can_len: 1,
+        can_data: [state.into(), 0, 0, 0, 0, 0, 0, 0],
+        ..Default::default()
+    }
+}
+
+/// Inputs of the [`HeartbeatProducer`] machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ProducerInput {
+    /// The given number of milliseconds has elapsed.
+    Tick(u32),
+    /// The local node entered the given NMT state.
+    State(NmtState),
+}
+
+/// A state machine producing the heartbeat of the local node.
+///
+/// After a reset the first tick yields the boot-up message, after which the
+/// node is pre-operational. From then on a heartbeat carrying the current
+/// NMT state is produced every period (object 0x1017, producer heartbeat time).
+/// A period of zero disables the heartbeat but not the boot-up message.
+pub struct HeartbeatProducer {
+    node: u8,
+    period: u16,
+    elapsed: u32,
+    state: NmtState,
+    boot_up: bool,
+    output: Option<CANFrame>,
+}
+
+impl HeartbeatProducer {
+    /// Creates a producer for `node` sending a heartbeat every `period` milliseconds.
+    pub fn new(node: u8, period: u16) -> Self {
+        Self {
+            node,
+            period,
+            elapsed: 0,
+            state: NmtState::BootUp,
+            boot_up: true,
+            output: None,
+        }
+    }
+
+    /// Returns the NMT state reported by the producer.
+    pub fn state(&self) -> NmtState {
+        self.state
+    }
+
+    /// Changes the producer heartbeat time, restarting the period.
+    pub fn set_period(&mut self, period: u16) {
+        self.period = period;
+        self.elapsed = 0;
+    }
+
+    /// Advances time by `ms` milliseconds and returns the frame to send, if any.
+    pub fn tick(&mut self, ms: u32) -> Option<CANFrame> {
+        self.transit(ProducerInput::Tick(ms));
+        self.observe()
+    }
+
+    fn on_tick(&mut self, ms: u32) {
+        if self.boot_up {
+            self.boot_up = false;
+            self.elapsed = 0;
+            self.output = Some(state_frame(self.node, NmtState::BootUp));
+            if self.state == NmtState::BootUp {
+                self.state = NmtState::PreOperational;
+            }
+            return;
+        }
+
+        if self.period == 0 {
+            return;
+        }
+
+        self.elapsed = self.elapsed.saturating_add(ms);
+        if self.elapsed >= u32::from(self.period) {
+            self.elapsed %= u32::from(self.period);
+            self.output = Some(state_frame(self.node, self.state));
+        }
+    }
+}
+
+impl MachineTrans<ProducerInput> for HeartbeatProducer {
+    type Observation = Option<CANFrame>;
+
+    /// Advances the heartbeat timer or changes the reported NMT state.
+    fn transit(&mut self, x: ProducerInput) {
+        self.output = None;
+
+        match x {
+            ProducerInput::Tick(ms) => self.on_tick(ms),
+            ProducerInput::State(state) => self.state = state,
+        }
+    }
+
+    /// Returns the frame produced by the last input.
+    fn observe(&self) -> Self::Observation {
+        self.output
+    }
+
+    /// Resets the producer as after a node reset, so the boot-up message is sent again.
+    fn initial(&mut self) {
+        self.elapsed = 0;
+        self.state = NmtState::BootUp;
+        self.boot_up = true;
+        self.output = None;
+    }
+}
+
+/// Inputs of the [`NodeGuarding`] machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GuardingInput {
+    /// The given number of milliseconds has elapsed.
+    Tick(u32),
+    /// The guarded node replied with the given state byte.
+    Response(u8),
+}
+
+/// Events reported by the [`NodeGuarding`] machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GuardingEvent {
+    /// The node did not reply correctly within its life time.
+    NodeLost,
+    /// A lost node replied again.
+    NodeRecovered,
+    /// The node replied with an unexpected toggle bit; the reply is ignored.
+    ToggleMismatch,
+}
+
+/// Observation of the [`NodeGuarding`] machine after the last input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GuardingOutput {
+    /// Remote request the caller has to transmit.
+    pub request: Option<CANFrame>,
+    /// Event caused by the last input.
+    pub event: Option<GuardingEvent>,
+}
+
+/// A state machine guarding a single node from the master side.
+///
+/// Every guard time the machine asks the caller to send a remote request on
+/// the node's error control COB-ID. The node replies with its NMT state and
+/// a toggle bit that alternates between replies. If no correct reply has been
+/// received for `life_time_factor` guard times the node is reported lost.
+pub struct NodeGuarding {
+    node: u8,
+    guard_time: u32,
+    life_time_factor: u8,
+    elapsed: u32,
+    missed: u8,
+    awaiting: bool,
+    toggle: bool,
+    lost: bool,
+    state: Option<NmtState>,
+    output: GuardingOutput,
+}
+
+impl NodeGuarding {
+    /// Creates a machine guarding `node` every `guard_time` milliseconds.
+    ///
+    /// A `guard_time` or `life_time_factor` of zero disables guarding.
+    pub fn new(node: u8, guard_time: u32, life_time_factor: u8) -> Self {
+        Self {
+            node,
+            guard_time,
+            life_time_factor,
+            elapsed: 0,
+            missed: 0,
+            awaiting: false,
+            toggle: false,
+            lost: false,
+            state: None,
+            output: GuardingOutput::default(),
+        }
+    }
+
+    /// Returns the node ID being guarded.
+    pub fn node(&self) -> u8 {
+        self.node
+    }
+
+    /// Returns the NMT state reported by the last correct reply.
+    pub fn state(&self) -> Option<NmtState> {
+        self.state
+    }
+
+    /// Returns `true` if the node is currently considered lost.
+    pub fn is_lost(&self) -> bool {
+        self.lost
+    }
+
+    /// Builds the remote request polling the node.
+    pub fn request_frame(&self) -> CANFrame {
+        CANFrame {
+            can_cobid: ERROR_CONTROL_COBID + u32::from(self.node),
+            is_rtr: true,
+            can_len: 1,
+            ..Default::default()
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.guard_time != 0 && self.life_time_factor != 0
+    }
+
+    fn tick(&mut self, ms: u32) {
+        if !self.enabled() {
+            return;
+        }
+
+        self.elapsed = self.elapsed.saturating_add(ms);
+        if self.elapsed < self.guard_time {
+            return;
+        }
+        let periods = self.elapsed / self.guard_time;
+        self.elapsed %= self.guard_time;
+
+        // every guard time passed without a reply counts as a miss, except
+        // the first one when no request was pending
+        let missed = if self.awaiting { periods } else { periods - 1 };
+        if missed > 0 {
+            let missed = u8::try_from(missed).unwrap_or(u8::MAX);
+            self.missed = self.missed.saturating_add(missed);
+            if self.missed >= self.life_time_factor && !self.lost {
+                self.lost = true;
+                self.output.event = Some(GuardingEvent::NodeLost);
+            }
+        }
+
+        self.awaiting = true;
+        self.output.request = Some(self.request_frame());
+    }
+
+    fn response(&mut self, x: u8) {
+        let toggle = x & TOGGLE_BIT != 0;
+
+        // a lost node may have been reset, so resynchronise on its toggle bit
+        if toggle != self.toggle && !self.lost {
+            self.output.event = Some(GuardingEvent::ToggleMismatch);
+            return;
+        }
+
+        self.toggle = !toggle;
+        self.state = NmtState::try_from(x & !TOGGLE_BIT).ok();
+        self.awaiting = false;
+        self.missed = 0;
+
+        if self.lost {
+            self.lost = false;
+            self.output.event = Some(GuardingEvent::NodeRecovered);
+        }
+    }
+}
+
+impl MachineTrans<GuardingInput> for NodeGuarding {
+    type Observation = GuardingOutput;
+
+    /// Advances the guard timer or processes a reply of the node.
+    fn transit(&mut self, x: GuardingInput) {
+        self.output = GuardingOutput::default();
+
+        match x {
+            GuardingInput::Tick(ms) => self.tick(ms),
+            GuardingInput::Response(x) => self.response(x),
+        }
+    }
+
+    /// Returns the request to send and the event caused by the last input.
+    fn observe(&self) -> Self::Observation {
+        self.output
+    }
+
+    /// Restarts guarding from scratch, keeping the configuration.
+    fn initial(&mut self) {
+        self.elapsed = 0;
+        self.missed = 0;
+        self.awaiting = false;
+        self.toggle = false;
+        self.lost = false;
+        self.state = None;
+        self.output = GuardingOutput::default();
+    }
+}
+
+/// An entry of the consumer heartbeat time (object 0x1016).
+///
+/// Encoded as a `u32` carrying the node ID in bits 16..23 and the heartbeat
+/// time in milliseconds in bits 0..15. A node ID or time of zero disables the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConsumerHeartbeatEntry {
+    /// The monitored node ID.
+    pub node: u8,
+    /// The heartbeat time in milliseconds.
+    pub time: u16,
+}
+
+impl ConsumerHeartbeatEntry {
+    /// Returns `true` if the entry monitors a node.
+    pub fn is_enabled(&self) -> bool {
+        self.node != 0 && self.time != 0
+    }
+}
+
+impl From<u32> for ConsumerHeartbeatEntry {
+    fn from(x: u32) -> Self {
+        Self {
+            node: (x >> 16) as u8,
+            time: x as u16,
+        }
+    }
+}
+
+impl From<ConsumerHeartbeatEntry> for u32 {
+    fn from(entry: ConsumerHeartbeatEntry) -> u32 {
+        (u32::from(entry.node) << 16) | u32::from(entry.time)
+    }
+}
+
+/// Inputs of the [`HeartbeatConsumers`] machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConsumerInput {
+    /// The given number of milliseconds has elapsed.
+    Tick(u32),
+    /// A heartbeat of `node` reporting `state` was received.
+    Heartbeat {
+        /// The node ID of the producer.
+        node: u8,
+        /// The NMT state reported by the producer.
+        state: NmtState,
+    },
+}
+
+/// A set of node IDs `0..=127`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeSet(u128);
+
+impl NodeSet {
+    /// Adds `node` to the set, node IDs above 127 are ignored.
+    pub fn insert(&mut self, node: u8) {
+        if node <= 127 {
+            self.0 |= 1 << node;
+        }
+    }
+
+    /// Returns `true` if `node` is in the set.
+    pub fn contains(&self, node: u8) -> bool {
+        node <= 127 && self.0 & (1 << node) != 0
+    }
+
+    /// Returns `true` if the set holds no node.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterates over the nodes of the set in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=127).filter(|node| self.contains(*node))
+    }
+}
+
+/// Observation of the [`HeartbeatConsumers`] machine after the last input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConsumerOutput {
+    /// Nodes whose heartbeat time ran out on the last input.
+    pub lost: NodeSet,
+    /// Lost node whose heartbeat was received again.
+    pub resumed: Option<u8>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Consumer {
+    entry: ConsumerHeartbeatEntry,
+    elapsed: u32,
+    state: Option<NmtState>,
+    lost: bool,
+}
+
+/// A state machine monitoring the heartbeats of up to `N` nodes.
+///
+/// It is configured with the values of the consumer heartbeat time object,
+/// sub-index `1..=N` holding a [`ConsumerHeartbeatEntry`]. Monitoring of a
+/// node starts with its first heartbeat. A node is lost when no heartbeat
+/// arrives within its heartbeat time, all nodes timing out on the same tick
+/// are reported together.
+pub struct HeartbeatConsumers<const N: usize> {
+    consumers: [Consumer; N],
+    output: ConsumerOutput,
+}
+
+impl<const N: usize> Default for HeartbeatConsumers<N> {
+    fn default() -> Self {
+        Self {
+            consumers: [Consumer::default(); N],
+            output: ConsumerOutput::default(),
+        }
+    }
+}
+
+impl<const N: usize> HeartbeatConsumers<N> {
+    /// Replaces the configuration with `entries`, stored from sub-index 1 on.
+    ///
+    /// On error the configuration is left empty.
+    pub fn load(&mut self, entries: &[u32]) -> Result<(), HeartbeatError> {
+        *self = Self::default();
+
+        for (i, value) in entries.iter().enumerate() {
+            let sub = u8::try_from(i + 1).unwrap_or(u8::MAX);
+            if let Err(e) = self.set(sub, *value) {
+                *self = Self::default();
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads sub-index `sub` of the consumer heartbeat time object.
+    ///
+    /// Sub-index 0 holds the number of entries.
+    pub fn get(&self, sub: u8) -> Option<u32> {
+        match sub {
+            0 => u32::try_from(N).ok(),
+            _ => self
+                .consumers
+                .get(usize::from(sub) - 1)
+                .map(|c| c.entry.into()),
+        }
+    }
+
+    /// Writes sub-index `sub` of the consumer heartbeat time object, restarting its monitoring.
+    pub fn set(&mut self, sub: u8, value: u32) -> Result<(), HeartbeatError> {
+        if sub == 0 || usize::from(sub) > N {
+            return Err(HeartbeatError::InvalidSubIndex(sub));
+        }
+
+        if (value >> 24) != 0 {
+            return Err(HeartbeatError::InvalidEntry(value));
+        }
+
+        let entry = ConsumerHeartbeatEntry::from(value);
+        if entry.node > 127 {
+            return Err(HeartbeatError::InvalidNodeId(entry.node));
+        }
+
+        let ix = usize::from(sub) - 1;
+        let duplicate = self.consumers.iter().enumerate().any(|(i, c)| {
+            i != ix && entry.is_enabled() && c.entry.is_enabled() && c.entry.node == entry.node
+        });
+        if duplicate {
+            return Err(HeartbeatError::DuplicateNode(entry.node));
+        }
+
+        self.consumers[ix] = Consumer {
+            entry,
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Returns the NMT state reported by the last heartbeat of `node`.
+    pub fn state(&self, node: u8) -> Option<NmtState> {
+        self.consumer(node).and_then(|c| c.state)
+    }
+
+    /// Returns `true` if `node` is monitored and currently considered lost.
+    pub fn is_lost(&self, node: u8) -> bool {
+        self.consumer(node).is_some_and(|c| c.lost)
+    }
+
+    fn consumer(&self, node: u8) -> Option<&Consumer> {
+        self.consumers
+            .iter()
+            .find(|c| c.entry.is_enabled() && c.entry.node == node)
+    }
+
+    fn tick(&mut self, ms: u32) {
+        for c in self.consumers.iter_mut() {
+            if !c.entry.is_enabled() || c.state.is_none() || c.lost {
+                continue;
+            }
+
+            c.elapsed = c.elapsed.saturating_add(ms);
+            if c.elapsed > u32::from(c.entry.time) {
+                c.lost = true;
+                self.output.lost.insert(c.entry.node);
+            }
+        }
+    }
+
+    fn heartbeat(&mut self, node: u8, state: NmtState) {
+        let Some(c) = self
+            .consumers
+            .iter_mut()
+            .find(|c| c.entry.is_enabled() && c.entry.node == node)
+        else {
+            return;
+        };
+
+        c.elapsed = 0;
+        c.state = Some(state);
+
+        if c.lost {
+            c.lost = false;
+            self.output.resumed = Some(node);
+        }
+    }
+}
+
+impl<const N: usize> MachineTrans<ConsumerInput> for HeartbeatConsumers<N> {
+    type Observation = ConsumerOutput;
+
+    /// Advances the heartbeat timers or processes a received heartbeat.
+    fn transit(&mut self, x: ConsumerInput) {
+        self.output = ConsumerOutput::default();
+
+        match x {
+            ConsumerInput::Tick(ms) => self.tick(ms),
+            ConsumerInput::Heartbeat { node, state } => self.heartbeat(node, state),
+        }
+    }
+
+    /// Returns the nodes lost or resumed by the last input.
+    fn observe(&self) -> Self::Observation {
+        self.output
+    }
+
+    /// Restarts monitoring of all nodes, keeping the configuration.
+    fn initial(&mut self) {
+        for c in self.consumers.iter_mut() {
+            *c = Consumer {
+                entry: c.entry,
+                ..Default::default()
+            };
+        }
+        self.output = ConsumerOutput::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_producer_boot_up() {
+        let mut producer = HeartbeatProducer::new(0x10, 100);
+
+        let frame = producer.tick(0).unwrap();
+
+        assert_eq!(frame.can_cobid, 0x710);
+        assert_eq!(frame.can_len, 1);
+        assert_eq!(frame.can_data[0], 0x00);
+        assert_eq!(producer.state(), NmtState::PreOperational);
+
+        // boot-up is sent exactly once
+        assert_eq!(producer.tick(0), None);
+        assert_eq!(producer.tick(99), None);
+        assert_eq!(producer.tick(1).unwrap().can_data[0], 0x7f);
+
+        producer.initial();
+
+        assert_eq!(producer.tick(0).unwrap().can_data[0], 0x00);
+    }
+
+    #[test]
+    fn test_heartbeat_producer_period() {
+        let mut producer = HeartbeatProducer::new(1, 250);
+        producer.tick(0);
+
+        let mut sent = 0;
+        for _ in 0..10 {
+            if producer.tick(100).is_some() {
+                sent += 1;
+            }
+        }
+
+        // 1000ms at a 250ms period
+        assert_eq!(sent, 4);
+
+        // long ticks saturate instead of overflowing
+        producer.tick(200);
+        assert!(producer.tick(u32::MAX).is_some());
+
+        producer.set_period(0);
+        assert_eq!(producer.tick(1000), None);
+    }
+
+    #[test]
+    fn test_heartbeat_producer_state_byte() {
+        let mut producer = HeartbeatProducer::new(1, 10);
+        producer.tick(0);
+
+        let states = [
+            (NmtState::Operational, 0x05),
+            (NmtState::Stopped, 0x04),
+            (NmtState::PreOperational, 0x7f),
+        ];
+
+        for (state, byte) in states {
+            producer.transit(ProducerInput::State(state));
+            assert_eq!(producer.observe(), None);
+            assert_eq!(producer.tick(10).unwrap().can_data[0], byte);
+        }
+    }
+
+    #[test]
+    fn test_node_guarding_toggle_sequence() {
+        let mut guard = NodeGuarding::new(5, 100, 3);
+
+        guard.transit(GuardingInput::Tick(50));
+        assert_eq!(guard.observe(), GuardingOutput::default());
+
+        for response in [0x05, 0x85, 0x05, 0x85] {
+            guard.transit(GuardingInput::Tick(50));
+            let request = guard.observe().request.unwrap();
+
+            assert_eq!(request.can_cobid, 0x705);
+            assert!(request.is_rtr);
+
+            guard.transit(GuardingInput::Response(response));
+            assert_eq!(guard.observe().event, None);
+            assert_eq!(guard.state(), Some(NmtState::Operational));
+
+            guard.transit(GuardingInput::Tick(50));
+        }
+
+        assert!(!guard.is_lost());
+    }
+
+    #[test]
+    fn test_node_guarding_node_lost() {
+        let mut guard = NodeGuarding::new(5, 100, 3);
+
+        guard.transit(GuardingInput::Tick(100));
+        guard.transit(GuardingInput::Response(0x7f));
+
+        // no replies from now on
+        guard.transit(GuardingInput::Tick(100));
+        assert_eq!(guard.observe().event, None);
+        guard.transit(GuardingInput::Tick(100));
+        assert_eq!(guard.observe().event, None);
+        guard.transit(GuardingInput::Tick(100));
+        assert_eq!(guard.observe().event, None);
+
+        guard.transit(GuardingInput::Tick(100));
+        let output = guard.observe();
+        assert_eq!(output.event, Some(GuardingEvent::NodeLost));
+        assert!(output.request.is_some());
+        assert!(guard.is_lost());
+
+        // lost is only reported once
+        guard.transit(GuardingInput::Tick(100));
+        assert_eq!(guard.observe().event, None);
+
+        // the node was reset, so its toggle starts over
+        guard.transit(GuardingInput::Response(0x00));
+        assert_eq!(guard.observe().event, Some(GuardingEvent::NodeRecovered));
+        assert_eq!(guard.state(), Some(NmtState::BootUp));
+    }
+
+    #[test]
+    fn test_node_guarding_long_tick() {
+        let mut guard = NodeGuarding::new(5, 100, 3);
+
+        guard.transit(GuardingInput::Tick(100));
+        guard.transit(GuardingInput::Response(0x7f));
+
+        // a single tick spanning the request and the whole life time loses the node
+        guard.transit(GuardingInput::Tick(400));
+        assert_eq!(guard.observe().event, Some(GuardingEvent::NodeLost));
+
+        let mut guard = NodeGuarding::new(5, 100, 3);
+
+        // the first guard time only sends the request
+        guard.transit(GuardingInput::Tick(300));
+        assert_eq!(guard.observe().event, None);
+        guard.transit(GuardingInput::Tick(100));
+        assert_eq!(guard.observe().event, Some(GuardingEvent::NodeLost));
+
+        guard.transit(GuardingInput::Tick(u32::MAX));
+        assert!(guard.is_lost());
+    }
+
+    #[test]
+    fn test_node_guarding_toggle_mismatch() {
+        let mut guard = NodeGuarding::new(5, 100, 2);
+
+        guard.transit(GuardingInput::Tick(100));
+        guard.transit(GuardingInput::Response(0x05));
+        assert_eq!(guard.observe().event, None);
+
+        // the toggle bit should have been set
+        guard.transit(GuardingInput::Tick(100));
+        guard.transit(GuardingInput::Response(0x05));
+        assert_eq!(guard.observe().event, Some(GuardingEvent::ToggleMismatch));
+
+        // the wrong reply counts as a missing one
+        guard.transit(GuardingInput::Tick(100));
+        guard.transit(GuardingInput::Response(0x05));
+        assert_eq!(guard.observe().event, Some(GuardingEvent::ToggleMismatch));
+
+        guard.transit(GuardingInput::Tick(100));
+        assert_eq!(guard.observe().event, Some(GuardingEvent::NodeLost));
+    }
+
+    #[test]
+    fn test_consumer_heartbeat_entry_layout() {
+        let entry = ConsumerHeartbeatEntry::from(0x0005_01f4);
+
+        assert_eq!(entry.node, 5);
+        assert_eq!(entry.time, 500);
+        assert!(entry.is_enabled());
+        assert_eq!(u32::from(entry), 0x0005_01f4);
+
+        assert!(!ConsumerHeartbeatEntry::from(0x0000_01f4).is_enabled());
+        assert!(!ConsumerHeartbeatEntry::from(0x0005_0000).is_enabled());
+    }
+
+    #[test]
+    fn test_heartbeat_consumers_config() {
+        let mut consumers: HeartbeatConsumers<3> = HeartbeatConsumers::default();
+
+        consumers.load(&[0x0005_01f4, 0x0006_0064]).unwrap();
+
+        assert_eq!(consumers.get(0), Some(3));
+        assert_eq!(consumers.get(1), Some(0x0005_01f4));
+        assert_eq!(consumers.get(3), Some(0));
+        assert_eq!(consumers.get(4), None);
+
+        // disabled entries may repeat a node ID
+        assert_eq!(consumers.set(3, 0x0005_0000), Ok(()));
+        assert_eq!(
+            consumers.set(3, 0x0005_0064),
+            Err(HeartbeatError::DuplicateNode(5))
+        );
+        assert_eq!(consumers.set(1, 0x0005_0064), Ok(()));
+        assert_eq!(consumers.set(0, 0), Err(HeartbeatError::InvalidSubIndex(0)));
+        assert_eq!(
+            consumers.set(1, 0x0080_0064),
+            Err(HeartbeatError::InvalidNodeId(0x80))
+        );
+        // node 5 is valid, the reserved byte is not
+        assert_eq!(
+            consumers.set(1, 0x0105_0064),
+            Err(HeartbeatError::InvalidEntry(0x0105_0064))
+        );
+
+        assert_eq!(
+            consumers.load(&[0x0005_01f4, 0x0005_0064]),
+            Err(HeartbeatError::DuplicateNode(5))
+        );
+        assert_eq!(consumers.get(1), Some(0));
+    }
+
+    #[test]
+    fn test_heartbeat_consumers_monitoring() {
+        let mut consumers: HeartbeatConsumers<2> = HeartbeatConsumers::default();
+        consumers.load(&[0x0005_0064, 0x0006_0064]).unwrap();
+
+        // monitoring starts with the first heartbeat
+        consumers.transit(ConsumerInput::Tick(1000));
+        assert_eq!(consumers.observe(), ConsumerOutput::default());
+
+        for node in [5, 6] {
+            consumers.transit(ConsumerInput::Heartbeat {
+                node,
+                state: NmtState::Operational,
+            });
+        }
+        assert_eq!(consumers.state(5), Some(NmtState::Operational));
+
+        consumers.transit(ConsumerInput::Tick(100));
+        assert_eq!(consumers.observe(), ConsumerOutput::default());
+
+        // both nodes time out at once and are reported together
+        consumers.transit(ConsumerInput::Tick(1));
+        let lost = consumers.observe().lost;
+        assert!(lost.contains(5) && lost.contains(6));
+        assert!(lost.iter().eq([5, 6]));
+
+        // lost is only reported once
+        consumers.transit(ConsumerInput::Tick(1000));
+        assert_eq!(consumers.observe(), ConsumerOutput::default());
+        assert!(consumers.is_lost(5));
+
+        consumers.transit(ConsumerInput::Heartbeat {
+            node: 5,
+            state: NmtState::PreOperational,
+        });
+        assert_eq!(consumers.observe().resumed, Some(5));
+        assert!(consumers.observe().lost.is_empty());
+        assert!(!consumers.is_lost(5));
+
+        // unknown nodes are ignored
+        consumers.transit(ConsumerInput::Heartbeat {
+            node: 7,
+            state: NmtState::Operational,
+        });
+        assert_eq!(consumers.observe(), ConsumerOutput::default());
+        assert_eq!(consumers.state(7), None);
+    }
+}
@@ -1,7 +1,36 @@
 #![no_std]
 //! # funcan-rs
 //!
+#[cfg(feature = "std")]
+extern crate std;
+
+/// Candump Log Format
+#[cfg(feature = "std")]
+pub mod candump;
+/// embedded-can Interoperability
+#[cfg(feature = "embedded-can")]
+pub mod embedded;
+/// Emergency Messages
+pub mod emcy;
+/// Crate-wide Errors
+pub mod error;
+/// Heartbeat and Node Guarding
+pub mod heartbeat;
+/// Layer Setting Services
+pub mod lss;
 /// Finite States Machines
 pub mod machine;
+/// Multiplexed PDOs
+pub mod mpdo;
+/// Network Management
+pub mod nmt;
+/// Process Data Objects
+pub mod pdo;
 /// Raw CAN Frames
 pub mod raw;
+/// SYNC Protocol
+pub mod sync;
+/// TIME Protocol
+pub mod time;
+
+pub use error::Error;
@@ -0,0 +1,294 @@
+//! # LSS Module
+//!
+//! The `lss` module provides the frame codec of the CANOpen layer setting
+//! services (CiA 305) used to configure the node ID of a device.
+
+use crate::raw::*;
+use core::fmt;
+
+/// COB-ID of LSS requests sent by the master.
+pub const LSS_MASTER_COBID: u32 = 0x7e5;
+
+/// COB-ID of LSS responses sent by the slave.
+pub const LSS_SLAVE_COBID: u32 = 0x7e4;
+
+/// Node ID of a device without a configured node ID.
+pub const LSS_UNCONFIGURED_NODE_ID: u8 = 0xff;
+
+const CS_SWITCH_STATE_GLOBAL: u8 = 0x04;
+const CS_CONFIGURE_NODE_ID: u8 = 0x11;
+const CS_STORE_CONFIGURATION: u8 = 0x17;
+
+/// Errors reported while decoding LSS frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LssError {
+    /// The command specifier is unknown or not supported.
+    UnknownCommandSpecifier(u8),
+    /// The switch state mode is neither waiting nor configuration.
+    InvalidMode(u8),
+    /// The node ID is neither in 1..=127 nor unconfigured.
+    InvalidNodeId(u8),
+}
+
+impl fmt::Display for LssError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LssError::UnknownCommandSpecifier(cs) => {
+                write!(f, "unknown LSS command specifier {:#04x}", cs)
+            }
+            LssError::InvalidMode(mode) => write!(f, "invalid LSS mode {}", mode),
+            LssError::InvalidNodeId(node) => write!(f, "invalid node ID {}", node),
+        }
+    }
+}
+
+impl core::error::Error for LssError {}
+
+/// LSS state of a slave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LssMode {
+    /// The slave ignores configuration requests.
+    Waiting,
+    /// The slave accepts configuration requests.
+    Configuration,
+}
+
+/// LSS requests sent by the master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LssRequest {
+    /// Switches all slaves into the given mode.
+    SwitchStateGlobal(LssMode),
+    /// Sets the pending node ID of the slave in configuration mode.
+    ConfigureNodeId(u8),
+    /// Makes the slave store its pending configuration.
+    StoreConfiguration,
+}
+
+/// LSS responses sent by the slave.
+///
+/// An `error` of zero means success, `0xff` means that `spec_error`
+/// carries an implementation specific error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LssResponse {
+    /// Result of a configure node-ID request.
+    ConfigureNodeId {
+        /// Error code, 1 means the node ID is out of range.
+        error: u8,
+        /// Implementation specific error.
+        spec_error: u8,
+    },
+    /// Result of a store configuration request.
+    StoreConfiguration {
+        /// Error code, 1 means storing is not supported, 2 means a storage media access error.
+        error: u8,
+        /// Implementation specific error.
+        spec_error: u8,
+    },
+}
+
+impl LssRequest {
+    /// Builds the request frame on [`LSS_MASTER_COBID`].
+    pub fn to_frame(&self) -> CANFrame {
+        CANFrame {
+            can_cobid: LSS_MASTER_COBID,
+            can_len: 8,
+            can_data: (*self).into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl LssResponse {
+    /// Builds the response frame on [`LSS_SLAVE_COBID`].
+    pub fn to_frame(&self) -> CANFrame {
+        CANFrame {
+            can_cobid: LSS_SLAVE_COBID,
+            can_len: 8,
+            can_data: (*self).into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if the response reports success.
+    pub fn is_success(&self) -> bool {
+        match self {
+            LssResponse::ConfigureNodeId { error, .. } => *error == 0,
+            LssResponse::StoreConfiguration { error, .. } => *error == 0,
+        }
+    }
+}
+
+impl From<LssRequest> for [u8; 8] {
+    fn from(req: LssRequest) -> [u8; 8] {
+        match req {
+            LssRequest::SwitchStateGlobal(mode) => {
+                let mode = match mode {
+                    LssMode::Waiting => 0,
+                    LssMode::Configuration => 1,
+                };
+                [CS_SWITCH_STATE_GLOBAL, mode, 0, 0, 0, 0, 0, 0]
+            }
+            LssRequest::ConfigureNodeId(node) => [CS_CONFIGURE_NODE_ID, node, 0, 0, 0, 0, 0, 0],
+            LssRequest::StoreConfiguration => [CS_STORE_CONFIGURATION, 0, 0, 0, 0, 0, 0, 0],
+        }
+    }
+}
+
+impl TryFrom<[u8; 8]> for LssRequest {
+    type Error = LssError;
+
+    fn try_from(data: [u8; 8]) -> Result<Self, Self::Error> {
+        match data[0] {
+            CS_SWITCH_STATE_GLOBAL => match data[1] {
+                0 => Ok(LssRequest::SwitchStateGlobal(LssMode::Waiting)),
+                1 => Ok(LssRequest::SwitchStateGlobal(LssMode::Configuration)),
+                x => Err(LssError::InvalidMode(x)),
+            },
+            CS_CONFIGURE_NODE_ID => match data[1] {
+                x @ (1..=127 | LSS_UNCONFIGURED_NODE_ID) => Ok(LssRequest::ConfigureNodeId(x)),
+                x => Err(LssError::InvalidNodeId(x)),
+            },
+            CS_STORE_CONFIGURATION => Ok(LssRequest::StoreConfiguration),
+            cs => Err(LssError::UnknownCommandSpecifier(cs)),
+        }
+    }
+}
+
+impl From<LssResponse> for [u8; 8] {
+    fn from(res: LssResponse) -> [u8; 8] {
+        match res {
+            LssResponse::ConfigureNodeId { error, spec_error } => {
+                [CS_CONFIGURE_NODE_ID, error, spec_error, 0, 0, 0, 0, 0]
+            }
+            LssResponse::StoreConfiguration { error, spec_error } => {
+                [CS_STORE_CONFIGURATION, error, spec_error, 0, 0, 0, 0, 0]
+            }
+        }
+    }
+}
+
+impl TryFrom<[u8; 8]> for LssResponse {
+    type Error = LssError;
+
+    fn try_from(data: [u8; 8]) -> Result<Self, Self::Error> {
+        match data[0] {
+            CS_CONFIGURE_NODE_ID => Ok(LssResponse::ConfigureNodeId {
+                error: data[1],
+                spec_error: data[2],
+            }),
+            CS_STORE_CONFIGURATION => Ok(LssResponse::StoreConfiguration {
+                error: data[1],
+                spec_error: data[2],
+            }),
+            cs => Err(LssError::UnknownCommandSpecifier(cs)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lss_request_encode_decode() {
+        let requests = [
+            (
+                LssRequest::SwitchStateGlobal(LssMode::Configuration),
+                [0x04, 0x01, 0, 0, 0, 0, 0, 0],
+            ),
+            (
+                LssRequest::SwitchStateGlobal(LssMode::Waiting),
+                [0x04, 0x00, 0, 0, 0, 0, 0, 0],
+            ),
+            (
+                LssRequest::ConfigureNodeId(0x0a),
+                [0x11, 0x0a, 0, 0, 0, 0, 0, 0],
+            ),
+            (
+                LssRequest::StoreConfiguration,
+                [0x17, 0x00, 0, 0, 0, 0, 0, 0],
+            ),
+        ];
+
+        for (req, data) in requests {
+            assert_eq!(<[u8; 8]>::from(req), data);
+            assert_eq!(LssRequest::try_from(data), Ok(req));
+        }
+
+        let frame = LssRequest::ConfigureNodeId(0x0a).to_frame();
+
+        assert_eq!(frame.can_cobid, 0x7e5);
+        assert_eq!(frame.can_len, 8);
+    }
+
+    #[test]
+    fn test_lss_request_invalid() {
+        assert_eq!(
+            LssRequest::try_from([0x04, 0x02, 0, 0, 0, 0, 0, 0]),
+            Err(LssError::InvalidMode(0x02))
+        );
+        assert_eq!(
+            LssRequest::try_from([0x11, 0x80, 0, 0, 0, 0, 0, 0]),
+            Err(LssError::InvalidNodeId(0x80))
+        );
+        assert_eq!(
+            LssRequest::try_from([0x11, 0xff, 0, 0, 0, 0, 0, 0]),
+            Ok(LssRequest::ConfigureNodeId(LSS_UNCONFIGURED_NODE_ID))
+        );
+        assert_eq!(
+            LssRequest::try_from([0x5e, 0x00, 0, 0, 0, 0, 0, 0]),
+            Err(LssError::UnknownCommandSpecifier(0x5e))
+        );
+    }
+
+    #[test]
+    fn test_lss_response_encode_decode() {
+        let data = [0x11, 0x00, 0x00, 0, 0, 0, 0, 0];
+        let res = LssResponse::try_from(data).unwrap();
+
+        assert!(res.is_success());
+        assert_eq!(<[u8; 8]>::from(res), data);
+
+        let data = [0x17, 0x02, 0x00, 0, 0, 0, 0, 0];
+        let res = LssResponse::try_from(data).unwrap();
+
+        assert_eq!(
+            res,
+            LssResponse::StoreConfiguration {
+                error: 2,
+                spec_error: 0
+            }
+        );
+        assert!(!res.is_success());
+        assert_eq!(res.to_frame().can_cobid, 0x7e4);
+    }
+
+    #[test]
+    fn test_lss_decode_corpus() {
+        let patterns = [0x00, 0x01, 0x7f, 0x80, 0xfe, 0xff];
+
+        for cs in 0..=u8::MAX {
+            for x in patterns {
+                let data = [cs, x, x, x, x, x, x, x];
+
+                match LssRequest::try_from(data) {
+                    Ok(req) => assert_eq!(LssRequest::try_from(<[u8; 8]>::from(req)), Ok(req)),
+                    Err(LssError::UnknownCommandSpecifier(y)) => assert_eq!(y, cs),
+                    Err(LssError::InvalidMode(y) | LssError::InvalidNodeId(y)) => assert_eq!(y, x),
+                }
+
+                match LssResponse::try_from(data) {
+                    Ok(res) => assert_eq!(<[u8; 8]>::from(res)[..3], data[..3]),
+                    Err(e) => assert_eq!(e, LssError::UnknownCommandSpecifier(cs)),
+                }
+            }
+        }
+    }
+}
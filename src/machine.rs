@@ -2,6 +2,8 @@
 //!
 //! This module offers traits for working with abstract finite state machines.
 
+use core::marker::PhantomData;
+
 /// A trait that represents a finite state machine (FSM).
 ///
 /// # Type Parameters
@@ -24,18 +26,18 @@ pub trait MachineTrans<X> {
     /// # Parameters
     ///
     /// - `x`: An input value of type `X` that influences the state change.
-    fn transit(self: &mut Self, x: X);
+    fn transit(&mut self, x: X);
 
     /// Makes an observation of the machine's current state.
     ///
     /// This method returns an abstract representation of the state or output
     /// of the machine as defined by the `Observation` associated type.
-    fn observe(self: &Self) -> Self::Observation;
+    fn observe(&self) -> Self::Observation;
 
     /// Resets the machine's state to its initial state.
     ///
     /// This method should bring the machine back to its starting condition.
-    fn initial(self: &mut Self);
+    fn initial(&mut self);
 }
 
 /// A trait for machines that have some final states and an associated value.
@@ -52,11 +54,15 @@ pub trait Final {
     ///
     /// - `None` if not in a final state.
     /// - `Some(val)` with `val` of type `FinalValue` if in a final state.
-    fn is_final(self: Self) -> Option<Self::FinalValue>;
+    #[allow(clippy::wrong_self_convention)]
+    fn is_final(self) -> Option<Self::FinalValue>;
 }
 
 /// Represents the composition of two finite state machines,
 /// where the output of the first machine (`M0`) serves as the input to the second machine (`M1`).
+///
+/// Machines that can fail, observing a [`FallibleFinal`], are composed with
+/// [`TryComp`] instead, so their errors are not lost.
 pub struct Comp<M0, M1> {
     /// The first state machine.
     pub m0: M0,
@@ -84,7 +90,7 @@ where
     /// Processes an input `x` by passing it through machine `M0`.
     ///
     /// If `M0` reaches a final state, its output is utilized as input for machine `M1`.
-    fn transit(self: &mut Self, x: X) {
+    fn transit(&mut self, x: X) {
         self.m0.transit(x);
         if let Some(y) = self.m0.observe().is_final() {
             // Reset `m0` to initial state
@@ -97,15 +103,543 @@ where
     /// Observes and returns the current state of the composed machine.
     ///
     /// The observation is based on `M1`.
-    fn observe(self: &Self) -> Self::Observation {
+    fn observe(&self) -> Self::Observation {
         self.m1.observe()
     }
 
     /// Resets both `M0` and `M1` to their initial states.
     ///
     /// Ensures that the entire composite machine starts at its initial configuration.
-    fn initial(self: &mut Self) {
+    fn initial(&mut self) {
+        self.m0.initial();
+        self.m1.initial();
+    }
+}
+
+/// Represents the composition of two finite state machines like [`Comp`],
+/// except that `M0` is not reset after handing a final value over to `M1`.
+///
+/// This suits streaming machines whose internal state (counters, running
+/// values) must survive across outputs. `M0` is then responsible for leaving
+/// its final state on the next input by itself.
+pub struct CompNoReset<M0, M1> {
+    /// The first state machine.
+    pub m0: M0,
+    /// The second state machine.
+    pub m1: M1,
+}
+
+impl<X, M0, M1> MachineTrans<X> for CompNoReset<M0, M1>
+where
+    M0: MachineTrans<X>,
+    <M0 as MachineTrans<X>>::Observation: Final,
+    M1: MachineTrans<<<M0 as MachineTrans<X>>::Observation as Final>::FinalValue>,
+{
+    /// Observable values of the composed machines derived from `M1`.
+    type Observation = <M1 as MachineTrans<
+        <<M0 as MachineTrans<X>>::Observation as Final>::FinalValue,
+    >>::Observation;
+
+    /// Processes an input `x` by passing it through machine `M0`.
+    ///
+    /// If `M0` reaches a final state, its output is utilized as input for machine `M1`
+    /// and `M0` keeps its state.
+    fn transit(&mut self, x: X) {
+        self.m0.transit(x);
+        if let Some(y) = self.m0.observe().is_final() {
+            self.m1.transit(y);
+        }
+    }
+
+    /// Observes and returns the current state of the composed machine.
+    ///
+    /// The observation is based on `M1`.
+    fn observe(&self) -> Self::Observation {
+        self.m1.observe()
+    }
+
+    /// Resets both `M0` and `M1` to their initial states.
+    fn initial(&mut self) {
+        self.m0.initial();
+        self.m1.initial();
+    }
+}
+
+/// Represents the composition of three finite state machines chained one after another:
+/// final values of `M0` are inputs of `M1` and final values of `M1` are inputs of `M2`.
+///
+/// It behaves like `Comp<Comp<M0, M1>, M2>` and is a convenience saving the
+/// nesting, with each stage reset right after handing its final value over
+/// to the next one.
+pub struct Comp3<M0, M1, M2> {
+    /// The first state machine.
+    pub m0: M0,
+    /// The second state machine.
+    pub m1: M1,
+    /// The third state machine.
+    pub m2: M2,
+}
+
+impl<X, M0, M1, M2> MachineTrans<X> for Comp3<M0, M1, M2>
+where
+    M0: MachineTrans<X>,
+    <M0 as MachineTrans<X>>::Observation: Final,
+    M1: MachineTrans<<<M0 as MachineTrans<X>>::Observation as Final>::FinalValue>,
+    <M1 as MachineTrans<<<M0 as MachineTrans<X>>::Observation as Final>::FinalValue>>::Observation:
+        Final,
+    M2: MachineTrans<
+        <<M1 as MachineTrans<<<M0 as MachineTrans<X>>::Observation as Final>::FinalValue>>::Observation as Final>::FinalValue,
+    >,
+{
+    /// Observable values of the composed machines derived from `M2`.
+    type Observation = <M2 as MachineTrans<
+        <<M1 as MachineTrans<<<M0 as MachineTrans<X>>::Observation as Final>::FinalValue>>::Observation as Final>::FinalValue,
+    >>::Observation;
+
+    /// Processes an input `x` by passing it through machine `M0`.
+    ///
+    /// Final values are handed over from `M0` to `M1` and from `M1` to `M2`,
+    /// resetting the machine that produced them.
+    fn transit(&mut self, x: X) {
+        self.m0.transit(x);
+        if let Some(y) = self.m0.observe().is_final() {
+            self.m0.initial();
+            self.m1.transit(y);
+            if let Some(z) = self.m1.observe().is_final() {
+                self.m1.initial();
+                self.m2.transit(z);
+            }
+        }
+    }
+
+    /// Observes and returns the current state of the composed machine.
+    ///
+    /// The observation is based on `M2`.
+    fn observe(&self) -> Self::Observation {
+        self.m2.observe()
+    }
+
+    /// Resets all three machines to their initial states.
+    fn initial(&mut self) {
+        self.m0.initial();
+        self.m1.initial();
+        self.m2.initial();
+    }
+}
+
+/// A trait for observations that can reach a final state or fail.
+///
+/// This is the error-aware counterpart of [`Final`], used by machines
+/// that can detect malformed input.
+pub trait FallibleFinal {
+    /// An associated type representing the value corresponding to a final state.
+    type FinalValue;
+
+    /// An associated type representing the error of a failed machine.
+    type Error;
+
+    /// Determines if the current state is a final or an error state.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` if not in a final state.
+    /// - `Ok(Some(val))` with `val` of type `FinalValue` if in a final state.
+    /// - `Err(err)` if the machine has failed.
+    fn try_final(self) -> Result<Option<Self::FinalValue>, Self::Error>;
+}
+
+impl<V, E> FallibleFinal for Result<Option<V>, E> {
+    type FinalValue = V;
+    type Error = E;
+
+    fn try_final(self) -> Result<Option<Self::FinalValue>, Self::Error> {
+        self
+    }
+}
+
+/// Represents the composition of two finite state machines where the first one can fail.
+///
+/// Like [`Comp`], final values of `M0` are fed into `M1`. In addition, when
+/// `M0` reports an error, `M0` is reset and the error is exposed in the
+/// observation of the composite until the next input arrives.
+pub struct TryComp<M0, M1, E> {
+    /// The first state machine.
+    pub m0: M0,
+    /// The second state machine.
+    pub m1: M1,
+    error: Option<E>,
+}
+
+impl<M0, M1, E> TryComp<M0, M1, E> {
+    /// Composes `m0` and `m1`.
+    pub fn new(m0: M0, m1: M1) -> Self {
+        Self {
+            m0,
+            m1,
+            error: None,
+        }
+    }
+}
+
+impl<X, M0, M1> MachineTrans<X>
+    for TryComp<M0, M1, <<M0 as MachineTrans<X>>::Observation as FallibleFinal>::Error>
+where
+    M0: MachineTrans<X>,
+    <M0 as MachineTrans<X>>::Observation: FallibleFinal,
+    <<M0 as MachineTrans<X>>::Observation as FallibleFinal>::Error: Clone,
+    M1: MachineTrans<<<M0 as MachineTrans<X>>::Observation as FallibleFinal>::FinalValue>,
+{
+    /// Observable values of `M1`, or the error reported by `M0`.
+    type Observation =
+        Result<
+            <M1 as MachineTrans<
+                <<M0 as MachineTrans<X>>::Observation as FallibleFinal>::FinalValue,
+            >>::Observation,
+            <<M0 as MachineTrans<X>>::Observation as FallibleFinal>::Error,
+        >;
+
+    /// Processes an input `x` by passing it through machine `M0`.
+    ///
+    /// If `M0` reaches a final state, its output is utilized as input for machine `M1`.
+    /// If `M0` fails, it is reset and the error is kept for observation.
+    fn transit(&mut self, x: X) {
+        self.error = None;
+        self.m0.transit(x);
+        match self.m0.observe().try_final() {
+            Ok(Some(y)) => {
+                self.m0.initial();
+                self.m1.transit(y);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                self.m0.initial();
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Returns the error of `M0` if the last input made it fail, otherwise the observation of `M1`.
+    fn observe(&self) -> Self::Observation {
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(self.m1.observe()),
+        }
+    }
+
+    /// Resets both `M0` and `M1` to their initial states and forgets any error.
+    fn initial(&mut self) {
+        self.error = None;
         self.m0.initial();
         self.m1.initial();
     }
 }
+
+/// Wraps a finite state machine and records its last `N` inputs.
+///
+/// The trace survives [`MachineTrans::initial`], so the inputs that led to a
+/// failure stay available after a combinator has reset the machine.
+///
+/// The inputs are kept in a ring buffer stored twice in a row, so the last
+/// `N` of them always form one contiguous slice. The buffer is filled with
+/// copies of the first input, which spares `X` a `Default` bound.
+pub struct Traced<M, X, const N: usize> {
+    /// The traced state machine.
+    pub machine: M,
+    trace: Option<[[X; N]; 2]>,
+    head: usize,
+    len: usize,
+}
+
+impl<M, X: Copy, const N: usize> Traced<M, X, N> {
+    /// Wraps `machine` with an empty trace.
+    pub fn new(machine: M) -> Self {
+        Self {
+            machine,
+            trace: None,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the recorded inputs, oldest first.
+    pub fn trace(&self) -> &[X] {
+        match &self.trace {
+            // the oldest input sits `len` slots before the next one written
+            Some(trace) => {
+                let start = (self.head + N - self.len) % N;
+                &trace.as_flattened()[start..start + self.len]
+            }
+            None => &[],
+        }
+    }
+
+    /// Forgets the recorded inputs.
+    pub fn clear_trace(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Overwrites the oldest input once the trace is full.
+    fn record(&mut self, x: X) {
+        if N == 0 {
+            return;
+        }
+
+        let trace = self.trace.get_or_insert([[x; N]; 2]);
+        trace[0][self.head] = x;
+        trace[1][self.head] = x;
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+}
+
+impl<X: Copy, M: MachineTrans<X>, const N: usize> MachineTrans<X> for Traced<M, X, N> {
+    /// Observable values of the traced machine.
+    type Observation = M::Observation;
+
+    /// Records the input `x` and passes it to the traced machine.
+    fn transit(&mut self, x: X) {
+        self.record(x);
+        self.machine.transit(x);
+    }
+
+    /// Returns the observation of the traced machine unchanged.
+    fn observe(&self) -> Self::Observation {
+        self.machine.observe()
+    }
+
+    /// Resets the traced machine, keeping the trace.
+    fn initial(&mut self) {
+        self.machine.initial();
+    }
+}
+
+/// Adapts the input of a finite state machine.
+///
+/// Every input `x` is converted by `f` before it is passed to the wrapped
+/// machine, e.g. to feed raw frames into a machine expecting decoded data.
+pub struct MapInput<M, F, X> {
+    /// The wrapped state machine.
+    pub machine: M,
+    f: F,
+    input: PhantomData<fn(X)>,
+}
+
+impl<M, F, X> MapInput<M, F, X> {
+    /// Wraps `machine`, converting its inputs with `f`.
+    pub fn new(machine: M, f: F) -> Self {
+        Self {
+            machine,
+            f,
+            input: PhantomData,
+        }
+    }
+}
+
+impl<X, Y, M, F> MachineTrans<X> for MapInput<M, F, X>
+where
+    M: MachineTrans<Y>,
+    F: Fn(X) -> Y,
+{
+    /// Observable values of the wrapped machine.
+    type Observation = M::Observation;
+
+    /// Converts the input `x` and passes it to the wrapped machine.
+    fn transit(&mut self, x: X) {
+        self.machine.transit((self.f)(x));
+    }
+
+    /// Returns the observation of the wrapped machine unchanged.
+    fn observe(&self) -> Self::Observation {
+        self.machine.observe()
+    }
+
+    /// Resets the wrapped machine.
+    fn initial(&mut self) {
+        self.machine.initial();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::CANFrame;
+
+    /// Running sum of its inputs, final after every input.
+    #[derive(Default)]
+    struct Sum {
+        sum: u32,
+    }
+
+    impl MachineTrans<u32> for Sum {
+        type Observation = Option<u32>;
+
+        fn transit(&mut self, x: u32) {
+            self.sum += x;
+        }
+
+        fn observe(&self) -> Self::Observation {
+            Some(self.sum)
+        }
+
+        fn initial(&mut self) {
+            self.sum = 0;
+        }
+    }
+
+    impl Final for Option<u32> {
+        type FinalValue = u32;
+
+        fn is_final(self) -> Option<u32> {
+            self
+        }
+    }
+
+    /// Remembers the last value it receives.
+    #[derive(Default)]
+    struct Last {
+        last: u32,
+    }
+
+    impl MachineTrans<u32> for Last {
+        type Observation = u32;
+
+        fn transit(&mut self, x: u32) {
+            self.last = x;
+        }
+
+        fn observe(&self) -> Self::Observation {
+            self.last
+        }
+
+        fn initial(&mut self) {
+            self.last = 0;
+        }
+    }
+
+    #[test]
+    fn test_comp_resets_m0() {
+        let mut machine = Comp {
+            m0: Sum::default(),
+            m1: Last::default(),
+        };
+
+        for x in [1, 2, 3] {
+            machine.transit(x);
+        }
+
+        // every sum started over
+        assert_eq!(machine.observe(), 3);
+    }
+
+    #[test]
+    fn test_comp_no_reset_keeps_m0() {
+        let mut machine = CompNoReset {
+            m0: Sum::default(),
+            m1: Last::default(),
+        };
+
+        for x in [1, 2, 3] {
+            machine.transit(x);
+        }
+
+        assert_eq!(machine.observe(), 6);
+
+        machine.initial();
+        machine.transit(4);
+
+        assert_eq!(machine.observe(), 4);
+    }
+
+    #[test]
+    fn test_traced_keeps_last_inputs() {
+        let mut machine: Traced<Sum, u32, 3> = Traced::new(Sum::default());
+
+        assert!(machine.trace().is_empty());
+
+        machine.transit(1);
+        machine.transit(2);
+
+        assert_eq!(machine.trace(), &[1, 2]);
+        assert_eq!(machine.observe(), Some(3));
+
+        for x in [3, 4, 5] {
+            machine.transit(x);
+        }
+
+        assert_eq!(machine.trace(), &[3, 4, 5]);
+        assert_eq!(machine.observe(), Some(15));
+
+        machine.transit(6);
+
+        assert_eq!(machine.trace(), &[4, 5, 6]);
+
+        machine.initial();
+
+        assert_eq!(machine.observe(), Some(0));
+        assert_eq!(machine.trace(), &[4, 5, 6]);
+
+        machine.clear_trace();
+
+        assert!(machine.trace().is_empty());
+    }
+
+    #[test]
+    fn test_map_input_converts_frames() {
+        let mut machine = MapInput::new(Sum::default(), |frame: CANFrame| {
+            u32::from(frame.can_data[0])
+        });
+
+        for x in [1, 2, 3] {
+            let frame = CANFrame {
+                can_len: 1,
+                can_data: [x, 0, 0, 0, 0, 0, 0, 0],
+                ..Default::default()
+            };
+            machine.transit(frame);
+        }
+
+        assert_eq!(machine.observe(), Some(6));
+
+        // adapted machines compose like any other
+        let mut machine = Comp {
+            m0: machine,
+            m1: Last::default(),
+        };
+
+        machine.transit(CANFrame {
+            can_len: 1,
+            can_data: [4, 0, 0, 0, 0, 0, 0, 0],
+            ..Default::default()
+        });
+
+        assert_eq!(machine.observe(), 10);
+        assert_eq!(machine.m0.machine.observe(), Some(0));
+    }
+
+    #[test]
+    fn test_map_input_feeds_heartbeat_frames() {
+        use crate::heartbeat::*;
+        use crate::nmt::NmtState;
+        use crate::raw::node_of;
+
+        let mut producer = HeartbeatProducer::new(5, 100);
+        let mut consumers: HeartbeatConsumers<1> = HeartbeatConsumers::default();
+        consumers.load(&[0x0005_00c8]).unwrap();
+
+        // received heartbeat frames become consumer inputs
+        let mut machine = MapInput::new(consumers, |frame: CANFrame| ConsumerInput::Heartbeat {
+            node: node_of(frame.can_cobid),
+            state: NmtState::try_from(frame.can_data[0]).unwrap(),
+        });
+
+        machine.transit(producer.tick(0).unwrap());
+        assert_eq!(machine.machine.state(5), Some(NmtState::BootUp));
+
+        producer.transit(ProducerInput::State(NmtState::Operational));
+        machine.transit(producer.tick(100).unwrap());
+
+        assert_eq!(machine.machine.state(5), Some(NmtState::Operational));
+        assert_eq!(machine.observe(), ConsumerOutput::default());
+    }
+}
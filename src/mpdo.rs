@@ -0,0 +1,276 @@
+//! # MPDO Module
+//!
+//! The `mpdo` module provides the frame codec of multiplexed PDOs (MPDO),
+//! which carry the address of the object they transfer along with its value.
+
+use crate::raw::*;
+use core::fmt;
+
+/// Address type bit of the first MPDO byte, set in destination address mode.
+pub const MPDO_DESTINATION_MODE: u8 = 0x80;
+
+/// Node ID addressing all nodes in destination address mode.
+pub const MPDO_ALL_NODES: u8 = 0x00;
+
+/// EMCY error code sent when a received DAM MPDO addresses an unavailable object.
+pub const EMCY_DAM_MPDO_NOT_PROCESSED: u16 = 0x8230;
+
+/// Number of data bytes in an MPDO frame.
+pub const MPDO_LEN: usize = 8;
+
+/// Errors reported while encoding or decoding MPDO frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MpdoError {
+    /// The frame does not carry an 8-byte payload.
+    InvalidLength(usize),
+    /// The destination or source node ID is above 127.
+    InvalidNodeId(u8),
+}
+
+impl fmt::Display for MpdoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MpdoError::InvalidLength(len) => write!(f, "invalid MPDO frame length {}", len),
+            MpdoError::InvalidNodeId(node) => write!(f, "invalid MPDO node ID {}", node),
+        }
+    }
+}
+
+impl core::error::Error for MpdoError {}
+
+/// A multiplexed PDO.
+///
+/// In both modes bytes 1..3 hold the object index (little endian) and its
+/// sub-index, bytes 4..7 hold up to 4 bytes of the object value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MpdoFrame {
+    /// Destination address mode: writes `data` to the object of node `node`,
+    /// or of all nodes for [`MPDO_ALL_NODES`].
+    Dam {
+        /// Destination node ID.
+        node: u8,
+        /// Index of the destination object.
+        index: u16,
+        /// Sub-index of the destination object.
+        sub: u8,
+        /// Value of the object.
+        data: [u8; 4],
+    },
+    /// Source address mode: publishes `data` of the object of node `node`.
+    Sam {
+        /// Source node ID.
+        node: u8,
+        /// Index of the source object.
+        index: u16,
+        /// Sub-index of the source object.
+        sub: u8,
+        /// Value of the object.
+        data: [u8; 4],
+    },
+}
+
+impl MpdoFrame {
+    /// Creates a destination address mode MPDO writing `value` to `node`.
+    pub fn dam(node: u8, index: u16, sub: u8, value: u32) -> Self {
+        MpdoFrame::Dam {
+            node,
+            index,
+            sub,
+            data: value.to_le_bytes(),
+        }
+    }
+
+    /// Creates a source address mode MPDO publishing `value` of `node`.
+    pub fn sam(node: u8, index: u16, sub: u8, value: u32) -> Self {
+        MpdoFrame::Sam {
+            node,
+            index,
+            sub,
+            data: value.to_le_bytes(),
+        }
+    }
+
+    /// Returns the destination or source node ID.
+    pub fn node(&self) -> u8 {
+        match self {
+            MpdoFrame::Dam { node, .. } | MpdoFrame::Sam { node, .. } => *node,
+        }
+    }
+
+    /// Returns the index and sub-index of the transferred object.
+    pub fn object(&self) -> (u16, u8) {
+        match self {
+            MpdoFrame::Dam { index, sub, .. } | MpdoFrame::Sam { index, sub, .. } => (*index, *sub),
+        }
+    }
+
+    /// Returns the value bytes of the transferred object.
+    pub fn data(&self) -> [u8; 4] {
+        match self {
+            MpdoFrame::Dam { data, .. } | MpdoFrame::Sam { data, .. } => *data,
+        }
+    }
+
+    /// Returns `true` for a destination address mode MPDO that `node` has to apply.
+    pub fn is_addressed_to(&self, node: u8) -> bool {
+        match self {
+            MpdoFrame::Dam { node: dst, .. } => *dst == MPDO_ALL_NODES || *dst == node,
+            MpdoFrame::Sam { .. } => false,
+        }
+    }
+
+    /// Builds the MPDO frame on the PDO COB-ID `cobid`.
+    pub fn to_frame(&self, cobid: u32) -> Result<CANFrame, MpdoError> {
+        Ok(CANFrame {
+            can_cobid: cobid,
+            can_len: MPDO_LEN,
+            can_data: (*self).try_into()?,
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<MpdoFrame> for [u8; 8] {
+    type Error = MpdoError;
+
+    /// Encodes the MPDO, rejecting node IDs that do not fit into 7 bits.
+    fn try_from(mpdo: MpdoFrame) -> Result<[u8; 8], Self::Error> {
+        let node = mpdo.node();
+        if u32::from(node) > CAN_NODE_MASK {
+            return Err(MpdoError::InvalidNodeId(node));
+        }
+
+        let (index, sub) = mpdo.object();
+        let mut data = [0; 8];
+
+        data[0] = match mpdo {
+            MpdoFrame::Dam { .. } => MPDO_DESTINATION_MODE | node,
+            MpdoFrame::Sam { .. } => node,
+        };
+        data[1..3].copy_from_slice(&index.to_le_bytes());
+        data[3] = sub;
+        data[4..8].copy_from_slice(&mpdo.data());
+        Ok(data)
+    }
+}
+
+impl From<[u8; 8]> for MpdoFrame {
+    fn from(data: [u8; 8]) -> Self {
+        let node = node_of(data[0].into());
+        let index = u16::from_le_bytes([data[1], data[2]]);
+        let sub = data[3];
+        let value = [data[4], data[5], data[6], data[7]];
+
+        if data[0] & MPDO_DESTINATION_MODE != 0 {
+            MpdoFrame::Dam {
+                node,
+                index,
+                sub,
+                data: value,
+            }
+        } else {
+            MpdoFrame::Sam {
+                node,
+                index,
+                sub,
+                data: value,
+            }
+        }
+    }
+}
+
+impl TryFrom<&CANFrame> for MpdoFrame {
+    type Error = MpdoError;
+
+    /// Decodes a received MPDO, checking that it carries all 8 bytes.
+    fn try_from(frame: &CANFrame) -> Result<Self, Self::Error> {
+        if frame.can_len != MPDO_LEN {
+            return Err(MpdoError::InvalidLength(frame.can_len));
+        }
+
+        Ok(Self::from(frame.can_data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mpdo_dam_encode_decode() {
+        let mpdo = MpdoFrame::dam(0x05, 0x6200, 0x01, 0x1234_5678);
+        let data: [u8; 8] = mpdo.try_into().unwrap();
+
+        assert_eq!(data, [0x85, 0x00, 0x62, 0x01, 0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(MpdoFrame::from(data), mpdo);
+        assert!(mpdo.is_addressed_to(0x05));
+        assert!(!mpdo.is_addressed_to(0x06));
+        assert!(MpdoFrame::dam(MPDO_ALL_NODES, 0x6200, 0x01, 0).is_addressed_to(0x06));
+
+        let frame = mpdo.to_frame(0x205).unwrap();
+
+        assert_eq!(frame.can_cobid, 0x205);
+        assert_eq!(MpdoFrame::try_from(&frame), Ok(mpdo));
+    }
+
+    #[test]
+    fn test_mpdo_sam_encode_decode() {
+        let data = [0x0a, 0x00, 0x60, 0x02, 0xff, 0x00, 0x00, 0x00];
+        let mpdo = MpdoFrame::from(data);
+
+        assert_eq!(mpdo, MpdoFrame::sam(0x0a, 0x6000, 0x02, 0xff));
+        assert_eq!(mpdo.node(), 0x0a);
+        assert_eq!(mpdo.object(), (0x6000, 0x02));
+        assert!(!mpdo.is_addressed_to(0x0a));
+        assert_eq!(<[u8; 8]>::try_from(mpdo), Ok(data));
+    }
+
+    #[test]
+    fn test_mpdo_invalid_node() {
+        // node 128 would be sent as node 0, i.e. to all nodes
+        for mpdo in [
+            MpdoFrame::dam(128, 0x6200, 0x01, 0),
+            MpdoFrame::sam(0x81, 0x6000, 0x02, 0),
+        ] {
+            assert_eq!(
+                <[u8; 8]>::try_from(mpdo),
+                Err(MpdoError::InvalidNodeId(mpdo.node()))
+            );
+            assert_eq!(
+                mpdo.to_frame(0x205),
+                Err(MpdoError::InvalidNodeId(mpdo.node()))
+            );
+        }
+
+        assert!(MpdoFrame::dam(127, 0x6200, 0x01, 0).to_frame(0x205).is_ok());
+    }
+
+    #[test]
+    fn test_mpdo_invalid_length() {
+        let mut frame = MpdoFrame::dam(0x05, 0x6200, 0x01, 0)
+            .to_frame(0x205)
+            .unwrap();
+        frame.can_len = 4;
+
+        assert_eq!(
+            MpdoFrame::try_from(&frame),
+            Err(MpdoError::InvalidLength(4))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_mpdo_serde() {
+        let mpdo = MpdoFrame::dam(0x05, 0x6200, 0x01, 0x1234_5678);
+        let json = serde_json::to_string(&mpdo).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"Dam":{"node":5,"index":25088,"sub":1,"data":[120,86,52,18]}}"#
+        );
+        assert_eq!(serde_json::from_str::<MpdoFrame>(&json).unwrap(), mpdo);
+    }
+}
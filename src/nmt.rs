@@ -0,0 +1,347 @@
+//! # NMT Module
+//!
+//! The `nmt` module provides the network management (NMT) states of CANOpen nodes,
+//! the NMT commands of the master and the NMT state machine of the local node.
+
+use crate::machine::*;
+use crate::raw::*;
+use core::fmt;
+
+/// COB-ID of NMT commands sent by the master.
+pub const NMT_COBID: u32 = 0x000;
+
+/// Node ID addressing all nodes with an NMT command.
+pub const NMT_ALL_NODES: u8 = 0x00;
+
+/// Errors reported while decoding NMT values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NmtError {
+    /// The value does not encode a known NMT state.
+    InvalidState(u8),
+    /// The value does not encode a known NMT command specifier.
+    InvalidCommand(u8),
+    /// The frame was not sent on the NMT COB-ID.
+    InvalidCobid(u32),
+    /// The frame does not carry a 2-byte payload.
+    InvalidLength(usize),
+}
+
+impl fmt::Display for NmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NmtError::InvalidState(x) => write!(f, "invalid NMT state {:#04x}", x),
+            NmtError::InvalidCommand(x) => write!(f, "invalid NMT command {:#04x}", x),
+            NmtError::InvalidCobid(cobid) => write!(f, "invalid NMT COB-ID {:#x}", cobid),
+            NmtError::InvalidLength(len) => write!(f, "invalid NMT frame length {}", len),
+        }
+    }
+}
+
+impl core::error::Error for NmtError {}
+
+/// NMT state of a node as reported by heartbeat and node guarding messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NmtState {
+    /// The node has just finished initialisation (boot-up message).
+    BootUp,
+    /// The node is stopped.
+    Stopped,
+    /// The node is operational.
+    Operational,
+    /// The node is pre-operational.
+    PreOperational,
+}
+
+impl From<NmtState> for u8 {
+    /// Encodes the state as defined by CiA 301.
+    fn from(state: NmtState) -> u8 {
+        match state {
+            NmtState::BootUp => 0x00,
+            NmtState::Stopped => 0x04,
+            NmtState::Operational => 0x05,
+            NmtState::PreOperational => 0x7f,
+        }
+    }
+}
+
+impl TryFrom<u8> for NmtState {
+    type Error = NmtError;
+
+    /// Decodes a state as defined by CiA 301.
+    fn try_from(x: u8) -> Result<Self, Self::Error> {
+        match x {
+            0x00 => Ok(NmtState::BootUp),
+            0x04 => Ok(NmtState::Stopped),
+            0x05 => Ok(NmtState::Operational),
+            0x7f => Ok(NmtState::PreOperational),
+            _ => Err(NmtError::InvalidState(x)),
+        }
+    }
+}
+
+/// NMT commands sent by the master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NmtCommand {
+    /// Start remote node, enters operational.
+    Start,
+    /// Stop remote node, enters stopped.
+    Stop,
+    /// Enter pre-operational.
+    EnterPreOperational,
+    /// Reset the application and communication parameters of the node.
+    ResetNode,
+    /// Reset the communication parameters of the node.
+    ResetCommunication,
+}
+
+impl From<NmtCommand> for u8 {
+    /// Encodes the command specifier as defined by CiA 301.
+    fn from(cmd: NmtCommand) -> u8 {
+        match cmd {
+            NmtCommand::Start => 0x01,
+            NmtCommand::Stop => 0x02,
+            NmtCommand::EnterPreOperational => 0x80,
+            NmtCommand::ResetNode => 0x81,
+            NmtCommand::ResetCommunication => 0x82,
+        }
+    }
+}
+
+impl TryFrom<u8> for NmtCommand {
+    type Error = NmtError;
+
+    /// Decodes a command specifier as defined by CiA 301.
+    fn try_from(x: u8) -> Result<Self, Self::Error> {
+        match x {
+            0x01 => Ok(NmtCommand::Start),
+            0x02 => Ok(NmtCommand::Stop),
+            0x80 => Ok(NmtCommand::EnterPreOperational),
+            0x81 => Ok(NmtCommand::ResetNode),
+            0x82 => Ok(NmtCommand::ResetCommunication),
+            _ => Err(NmtError::InvalidCommand(x)),
+        }
+    }
+}
+
+/// An NMT command addressed to a node, or to all nodes for [`NMT_ALL_NODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NmtRequest {
+    /// The command to execute.
+    pub command: NmtCommand,
+    /// The addressed node ID.
+    pub node: u8,
+}
+
+impl NmtRequest {
+    /// Returns `true` if the command has to be executed by `node`.
+    pub fn is_addressed_to(&self, node: u8) -> bool {
+        self.node == NMT_ALL_NODES || self.node == node
+    }
+
+    /// Builds the command frame on [`NMT_COBID`].
+    pub fn to_frame(&self) -> CANFrame {
+        CANFrame::builder()
+            .cobid(NMT_COBID)
+            .data(&[self.command.into(), self.node])
+            .build()
+    }
+}
+
+impl TryFrom<&CANFrame> for NmtRequest {
+    type Error = NmtError;
+
+    /// Decodes a received NMT command frame, checking its COB-ID and length.
+    fn try_from(frame: &CANFrame) -> Result<Self, Self::Error> {
+        if frame.can_cobid != NMT_COBID || frame.is_extended {
+            return Err(NmtError::InvalidCobid(frame.can_cobid));
+        }
+
+        if frame.can_len != 2 {
+            return Err(NmtError::InvalidLength(frame.can_len));
+        }
+
+        Ok(Self {
+            command: NmtCommand::try_from(frame.can_data[0])?,
+            node: frame.can_data[1],
+        })
+    }
+}
+
+/// NMT state machine of the local node.
+///
+/// The machine starts in initialisation, reported as [`NmtState::BootUp`],
+/// and ignores all commands until [`SlaveMachine::boot_completed`] moves it
+/// to pre-operational. Its observation can be passed as
+/// [`ProducerInput::State`](crate::heartbeat::ProducerInput::State) to the
+/// heartbeat producer, which then sends the boot-up message, and as
+/// [`TpdoInput::State`](crate::pdo::TpdoInput::State) to TPDOs, which are
+/// only sent while operational.
+pub struct SlaveMachine {
+    state: NmtState,
+}
+
+impl Default for SlaveMachine {
+    fn default() -> Self {
+        Self {
+            state: NmtState::BootUp,
+        }
+    }
+}
+
+impl SlaveMachine {
+    /// Returns the current NMT state.
+    pub fn state(&self) -> NmtState {
+        self.state
+    }
+
+    /// Finishes initialisation and enters pre-operational.
+    pub fn boot_completed(&mut self) {
+        if self.state == NmtState::BootUp {
+            self.state = NmtState::PreOperational;
+        }
+    }
+
+    /// Returns `true` if SDO transfers are allowed.
+    pub fn sdo_enabled(&self) -> bool {
+        matches!(self.state, NmtState::PreOperational | NmtState::Operational)
+    }
+
+    /// Returns `true` if EMCY, SYNC and TIME messages are allowed.
+    pub fn emcy_enabled(&self) -> bool {
+        self.sdo_enabled()
+    }
+
+    /// Returns `true` if PDOs are allowed.
+    pub fn pdo_enabled(&self) -> bool {
+        self.state == NmtState::Operational
+    }
+}
+
+impl MachineTrans<NmtCommand> for SlaveMachine {
+    type Observation = NmtState;
+
+    /// Executes an NMT command, commands received during initialisation are ignored.
+    fn transit(&mut self, cmd: NmtCommand) {
+        if self.state == NmtState::BootUp {
+            return;
+        }
+
+        self.state = match cmd {
+            NmtCommand::Start => NmtState::Operational,
+            NmtCommand::Stop => NmtState::Stopped,
+            NmtCommand::EnterPreOperational => NmtState::PreOperational,
+            NmtCommand::ResetNode | NmtCommand::ResetCommunication => NmtState::BootUp,
+        };
+    }
+
+    /// Returns the current NMT state.
+    fn observe(&self) -> Self::Observation {
+        self.state
+    }
+
+    /// Returns to initialisation.
+    fn initial(&mut self) {
+        self.state = NmtState::BootUp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nmt_state_encode_decode() {
+        let states = [
+            NmtState::BootUp,
+            NmtState::Stopped,
+            NmtState::Operational,
+            NmtState::PreOperational,
+        ];
+
+        for state in states {
+            assert_eq!(NmtState::try_from(u8::from(state)), Ok(state));
+        }
+
+        assert_eq!(NmtState::try_from(0x01), Err(NmtError::InvalidState(0x01)));
+    }
+
+    #[test]
+    fn test_nmt_request_encode_decode() {
+        let req = NmtRequest {
+            command: NmtCommand::Start,
+            node: 0x05,
+        };
+        let frame = req.to_frame();
+
+        assert_eq!(frame.can_cobid, 0x000);
+        assert_eq!(frame.can_len, 2);
+        assert_eq!(&frame.can_data[..2], &[0x01, 0x05]);
+        assert_eq!(NmtRequest::try_from(&frame), Ok(req));
+        assert!(req.is_addressed_to(0x05));
+        assert!(!req.is_addressed_to(0x06));
+
+        let mut frame = CANFrame::builder().data(&[0x03, 0x00]).build();
+
+        assert_eq!(
+            NmtRequest::try_from(&frame),
+            Err(NmtError::InvalidCommand(0x03))
+        );
+
+        frame.can_len = 1;
+        assert_eq!(
+            NmtRequest::try_from(&frame),
+            Err(NmtError::InvalidLength(1))
+        );
+    }
+
+    #[test]
+    fn test_slave_machine_transitions() {
+        let mut nmt = SlaveMachine::default();
+
+        // commands are ignored during initialisation
+        nmt.transit(NmtCommand::Start);
+        assert_eq!(nmt.observe(), NmtState::BootUp);
+        assert!(!nmt.sdo_enabled());
+
+        nmt.boot_completed();
+        assert_eq!(nmt.observe(), NmtState::PreOperational);
+        assert!(nmt.sdo_enabled());
+        assert!(!nmt.pdo_enabled());
+
+        let transitions = [
+            (NmtCommand::Start, NmtState::Operational),
+            (NmtCommand::Stop, NmtState::Stopped),
+            (NmtCommand::EnterPreOperational, NmtState::PreOperational),
+            (NmtCommand::Stop, NmtState::Stopped),
+            (NmtCommand::Start, NmtState::Operational),
+            (NmtCommand::EnterPreOperational, NmtState::PreOperational),
+            (NmtCommand::Start, NmtState::Operational),
+        ];
+
+        for (cmd, state) in transitions {
+            nmt.transit(cmd);
+            assert_eq!(nmt.observe(), state);
+        }
+
+        assert!(nmt.pdo_enabled());
+        assert!(nmt.sdo_enabled());
+
+        nmt.transit(NmtCommand::Stop);
+        assert!(!nmt.pdo_enabled());
+        assert!(!nmt.sdo_enabled());
+        assert!(!nmt.emcy_enabled());
+
+        for reset in [NmtCommand::ResetNode, NmtCommand::ResetCommunication] {
+            nmt.boot_completed();
+            nmt.transit(reset);
+            assert_eq!(nmt.observe(), NmtState::BootUp);
+        }
+    }
+}
@@ -0,0 +1,889 @@
+//! # PDO Module
+//!
+//! The `pdo` module provides process data objects (PDO): the COB-IDs of the
+//! predefined connection set, the mapping of objects into PDO data and the
+//! transmission of TPDOs.
+
+use crate::machine::*;
+use crate::nmt::NmtState;
+use crate::raw::*;
+use core::fmt;
+
+/// Base COB-IDs of TPDO1..TPDO4, the node ID is added to them.
+pub const TPDO_COBIDS: [u32; 4] = [0x180, 0x280, 0x380, 0x480];
+
+/// Base COB-IDs of RPDO1..RPDO4, the node ID is added to them.
+pub const RPDO_COBIDS: [u32; 4] = [0x200, 0x300, 0x400, 0x500];
+
+/// Errors reported while processing PDOs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PdoError {
+    /// The PDO number is not in 1..=4.
+    InvalidPdoNumber(u8),
+    /// The node ID is not in 1..=127.
+    InvalidNodeId(u8),
+    /// The COB-ID is not a PDO COB-ID of the predefined connection set.
+    InvalidCobid(u32),
+    /// The transmission type is reserved or not supported.
+    InvalidTransmissionType(u8),
+    /// More than 8 bytes of PDO data were given.
+    InvalidLength(usize),
+    /// A mapping entry has a bit length of zero or above 64.
+    InvalidMappingLength(u8),
+    /// The mapped objects exceed the 64 bits of a PDO, holds the total bit length.
+    MappingTooLong(usize),
+    /// The mapping has no room for another entry.
+    TooManyMappings,
+    /// The number of values does not match the number of mapped objects.
+    InvalidValueCount(usize),
+}
+
+impl fmt::Display for PdoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdoError::InvalidPdoNumber(n) => write!(f, "invalid PDO number {}", n),
+            PdoError::InvalidNodeId(node) => write!(f, "invalid node ID {}", node),
+            PdoError::InvalidCobid(cobid) => write!(f, "invalid PDO COB-ID {:#x}", cobid),
+            PdoError::InvalidTransmissionType(x) => write!(f, "invalid transmission type {}", x),
+            PdoError::InvalidLength(len) => write!(f, "invalid PDO length {}", len),
+            PdoError::InvalidMappingLength(bits) => write!(f, "invalid mapping length {}", bits),
+            PdoError::MappingTooLong(bits) => write!(f, "mapping of {} bits exceeds a PDO", bits),
+            PdoError::TooManyMappings => write!(f, "too many mapped objects"),
+            PdoError::InvalidValueCount(n) => write!(f, "invalid number of values {}", n),
+        }
+    }
+}
+
+impl core::error::Error for PdoError {}
+
+/// Direction of a PDO, seen from the node owning it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PdoDirection {
+    /// Transmit PDO, produced by the node.
+    Tx,
+    /// Receive PDO, consumed by the node.
+    Rx,
+}
+
+/// A PDO of the predefined connection set.
+///
+/// # Fields
+///
+/// * `direction` - Whether the PDO is transmitted or received by `node`.
+/// * `number` - The PDO number, 1..=4.
+/// * `node` - The node ID owning the PDO.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PdoId {
+    direction: PdoDirection,
+    number: u8,
+    node: u8,
+}
+
+impl PdoId {
+    /// Creates the identifier of PDO `number` of `node`.
+    pub fn new(direction: PdoDirection, number: u8, node: u8) -> Result<Self, PdoError> {
+        if !(1..=4).contains(&number) {
+            return Err(PdoError::InvalidPdoNumber(number));
+        }
+
+        if !(1..=127).contains(&node) {
+            return Err(PdoError::InvalidNodeId(node));
+        }
+
+        Ok(Self {
+            direction,
+            number,
+            node,
+        })
+    }
+
+    /// Creates the identifier of TPDO `number` of `node`.
+    pub fn tx(number: u8, node: u8) -> Result<Self, PdoError> {
+        Self::new(PdoDirection::Tx, number, node)
+    }
+
+    /// Creates the identifier of RPDO `number` of `node`.
+    pub fn rx(number: u8, node: u8) -> Result<Self, PdoError> {
+        Self::new(PdoDirection::Rx, number, node)
+    }
+
+    /// Returns the direction of the PDO.
+    pub fn direction(&self) -> PdoDirection {
+        self.direction
+    }
+
+    /// Returns the PDO number, 1..=4.
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    /// Returns the node ID owning the PDO.
+    pub fn node(&self) -> u8 {
+        self.node
+    }
+
+    /// Returns the COB-ID of the PDO in the predefined connection set.
+    pub fn cobid(&self) -> u32 {
+        let bases = match self.direction {
+            PdoDirection::Tx => &TPDO_COBIDS,
+            PdoDirection::Rx => &RPDO_COBIDS,
+        };
+
+        bases[usize::from(self.number) - 1] + u32::from(self.node)
+    }
+}
+
+impl TryFrom<u32> for PdoId {
+    type Error = PdoError;
+
+    /// Decodes a PDO COB-ID of the predefined connection set.
+    fn try_from(cobid: u32) -> Result<Self, Self::Error> {
+        let base = cobid & !CAN_NODE_MASK;
+        let node = node_of(cobid);
+
+        let found = |bases: &[u32; 4]| bases.iter().position(|&b| b == base);
+        let (direction, ix) = match (found(&TPDO_COBIDS), found(&RPDO_COBIDS)) {
+            (Some(ix), _) => (PdoDirection::Tx, ix),
+            (_, Some(ix)) => (PdoDirection::Rx, ix),
+            _ => return Err(PdoError::InvalidCobid(cobid)),
+        };
+
+        Self::new(direction, ix as u8 + 1, node).map_err(|_| PdoError::InvalidCobid(cobid))
+    }
+}
+
+/// An object mapped into a PDO, an entry of the PDO mapping parameter.
+///
+/// Encoded as a `u32` carrying the index in bits 16..31, the sub-index in
+/// bits 8..15 and the length of the object in bits in bits 0..7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PdoMappingEntry {
+    /// Index of the mapped object.
+    pub index: u16,
+    /// Sub-index of the mapped object.
+    pub sub: u8,
+    /// Length of the mapped object in bits.
+    pub bits: u8,
+}
+
+impl From<u32> for PdoMappingEntry {
+    fn from(x: u32) -> Self {
+        Self {
+            index: (x >> 16) as u16,
+            sub: (x >> 8) as u8,
+            bits: x as u8,
+        }
+    }
+}
+
+impl From<PdoMappingEntry> for u32 {
+    fn from(entry: PdoMappingEntry) -> u32 {
+        (u32::from(entry.index) << 16) | (u32::from(entry.sub) << 8) | u32::from(entry.bits)
+    }
+}
+
+/// The mapping of up to `N` objects into the data of a PDO.
+///
+/// Objects are laid out in mapping order starting at the least significant
+/// bit of the first data byte, without padding, so objects of any bit length
+/// may straddle byte boundaries. Values are exchanged as `u64` holding the
+/// object in its low bits.
+pub struct PdoMapping<const N: usize> {
+    entries: [PdoMappingEntry; N],
+    len: usize,
+}
+
+impl<const N: usize> Default for PdoMapping<N> {
+    fn default() -> Self {
+        Self {
+            entries: [PdoMappingEntry::default(); N],
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> PdoMapping<N> {
+    /// Appends an object to the mapping.
+    pub fn push(&mut self, entry: PdoMappingEntry) -> Result<(), PdoError> {
+        if entry.bits == 0 || entry.bits > 64 {
+            return Err(PdoError::InvalidMappingLength(entry.bits));
+        }
+
+        let bits = self.bits() + usize::from(entry.bits);
+        if bits > 64 {
+            return Err(PdoError::MappingTooLong(bits));
+        }
+
+        if self.len == N {
+            return Err(PdoError::TooManyMappings);
+        }
+
+        self.entries[self.len] = entry;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes all mapped objects.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Returns the mapped objects.
+    pub fn entries(&self) -> &[PdoMappingEntry] {
+        &self.entries[..self.len]
+    }
+
+    /// Returns the total length of the mapped objects in bits.
+    pub fn bits(&self) -> usize {
+        self.entries().iter().map(|e| usize::from(e.bits)).sum()
+    }
+
+    /// Returns the number of data bytes of the PDO.
+    pub fn len_bytes(&self) -> usize {
+        self.bits().div_ceil(8)
+    }
+
+    /// Packs one value per mapped object into `data`, returning the PDO length in bytes.
+    ///
+    /// Bits of a value above the length of its object are ignored.
+    pub fn pack(&self, values: &[u64], data: &mut [u8; 8]) -> Result<usize, PdoError> {
+        if values.len() != self.len {
+            return Err(PdoError::InvalidValueCount(values.len()));
+        }
+
+        let mut packed = 0u64;
+        let mut offset = 0;
+        for (entry, value) in self.entries().iter().zip(values) {
+            packed |= (value & mask(entry.bits)) << offset;
+            offset += u32::from(entry.bits);
+        }
+
+        *data = packed.to_le_bytes();
+        Ok(self.len_bytes())
+    }
+
+    /// Unpacks the values of the mapped objects from `data`, one per object.
+    pub fn unpack(&self, data: &[u8; 8], values: &mut [u64]) -> Result<(), PdoError> {
+        if values.len() != self.len {
+            return Err(PdoError::InvalidValueCount(values.len()));
+        }
+
+        let packed = u64::from_le_bytes(*data);
+        let mut offset = 0;
+        for (entry, value) in self.entries().iter().zip(values) {
+            *value = packed.checked_shr(offset).unwrap_or(0) & mask(entry.bits);
+            offset += u32::from(entry.bits);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a mask of the `bits` least significant bits.
+fn mask(bits: u8) -> u64 {
+    u64::MAX >> (64 - u32::from(bits))
+}
+
+/// Transmission type of a TPDO (sub-index 2 of its communication parameter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransmissionType {
+    /// Sent on the first SYNC after an event (type 0).
+    SynchronousAcyclic,
+    /// Sent on every n-th SYNC (types 1..=240), other values are rejected
+    /// by [`Tpdo`].
+    SynchronousCyclic(u8),
+    /// Sent on every event (types 254 and 255).
+    EventDriven,
+}
+
+impl From<TransmissionType> for u8 {
+    /// Encodes the transmission type, event-driven TPDOs use the device profile type 255.
+    fn from(t: TransmissionType) -> u8 {
+        match t {
+            TransmissionType::SynchronousAcyclic => 0,
+            TransmissionType::SynchronousCyclic(n) => n,
+            TransmissionType::EventDriven => 255,
+        }
+    }
+}
+
+impl TransmissionType {
+    /// Checks that a cyclic transmission type is within 1..=240.
+    fn validate(self) -> Result<Self, PdoError> {
+        match self {
+            TransmissionType::SynchronousCyclic(n @ (0 | 241..)) => {
+                Err(PdoError::InvalidTransmissionType(n))
+            }
+            t => Ok(t),
+        }
+    }
+}
+
+impl TryFrom<u8> for TransmissionType {
+    type Error = PdoError;
+
+    /// Decodes a transmission type, the RTR-only types 252 and 253 are not supported.
+    fn try_from(x: u8) -> Result<Self, Self::Error> {
+        match x {
+            0 => Ok(TransmissionType::SynchronousAcyclic),
+            1..=240 => Ok(TransmissionType::SynchronousCyclic(x)),
+            254 | 255 => Ok(TransmissionType::EventDriven),
+            _ => Err(PdoError::InvalidTransmissionType(x)),
+        }
+    }
+}
+
+/// Inputs of the [`Tpdo`] machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TpdoInput {
+    /// A SYNC message was received.
+    Sync,
+    /// An application event occurred, e.g. the mapped data changed.
+    Event,
+    /// The given number of milliseconds has elapsed.
+    Tick(u32),
+    /// The local node entered the given NMT state.
+    State(NmtState),
+}
+
+/// A state machine deciding when a TPDO is transmitted.
+///
+/// The machine holds the PDO data set by the application and yields the
+/// frame to send according to its [`TransmissionType`].
+///
+/// Event-driven TPDOs additionally support an inhibit time (sub-index 3, in
+/// multiples of 100µs) enforcing a minimum gap between two transmissions, and
+/// an event timer (sub-index 5, in milliseconds) transmitting the PDO
+/// periodically. A transmission due within the inhibit time is held back
+/// until the inhibit time has passed. Both are disabled by zero. Time is
+/// tracked in units of 100µs, so an inhibit time ends on the first tick at or
+/// after it.
+///
+/// PDOs are only transmitted in the operational NMT state, see
+/// [`TpdoInput::State`]. SYNCs, events and ticks received in any other state
+/// are dropped, and the SYNC count and timers restart once the node becomes
+/// operational.
+pub struct Tpdo {
+    cobid: u32,
+    state: NmtState,
+    transmission: TransmissionType,
+    inhibit: u16,
+    event_timer: u16,
+    data: [u8; 8],
+    len: usize,
+    sync_count: u8,
+    pending: bool,
+    // in multiples of 100µs
+    since_last: u32,
+    // in multiples of 100µs
+    since_event: u32,
+    output: Option<CANFrame>,
+}
+
+impl Tpdo {
+    /// Creates a TPDO sent on `cobid`, see [`PdoId::cobid`].
+    pub fn new(cobid: u32, transmission: TransmissionType) -> Result<Self, PdoError> {
+        Ok(Self {
+            cobid,
+            state: NmtState::BootUp,
+            transmission: transmission.validate()?,
+            inhibit: 0,
+            event_timer: 0,
+            data: [0; 8],
+            len: 0,
+            sync_count: 0,
+            pending: false,
+            since_last: u32::MAX,
+            since_event: 0,
+            output: None,
+        })
+    }
+
+    /// Returns the transmission type.
+    pub fn transmission(&self) -> TransmissionType {
+        self.transmission
+    }
+
+    /// Changes the transmission type, restarting the SYNC count.
+    pub fn set_transmission(&mut self, transmission: TransmissionType) -> Result<(), PdoError> {
+        self.transmission = transmission.validate()?;
+        self.sync_count = 0;
+        self.pending = false;
+        Ok(())
+    }
+
+    /// Changes the inhibit time, in multiples of 100µs.
+    pub fn set_inhibit(&mut self, inhibit: u16) {
+        self.inhibit = inhibit;
+    }
+
+    /// Changes the event timer, in milliseconds, restarting it.
+    pub fn set_event_timer(&mut self, event_timer: u16) {
+        self.event_timer = event_timer;
+        self.since_event = 0;
+    }
+
+    /// Sets the data sent with the next transmission.
+    ///
+    /// This does not trigger a transmission, see [`TpdoInput::Event`].
+    pub fn set_data(&mut self, data: &[u8]) -> Result<(), PdoError> {
+        if data.len() > 8 {
+            return Err(PdoError::InvalidLength(data.len()));
+        }
+
+        self.data = [0; 8];
+        self.data[..data.len()].copy_from_slice(data);
+        self.len = data.len();
+        Ok(())
+    }
+
+    fn frame(&self) -> CANFrame {
+        CANFrame {
+            can_cobid: self.cobid,
+            can_len: self.len,
+            can_data: self.data,
+            ..Default::default()
+        }
+    }
+
+    fn on_state(&mut self, state: NmtState) {
+        if state != self.state {
+            self.state = state;
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.sync_count = 0;
+        self.pending = false;
+        self.since_last = u32::MAX;
+        self.since_event = 0;
+    }
+
+    fn sync(&mut self) {
+        match self.transmission {
+            TransmissionType::SynchronousAcyclic => {
+                if self.pending {
+                    self.pending = false;
+                    self.output = Some(self.frame());
+                }
+            }
+            TransmissionType::SynchronousCyclic(n) => {
+                self.sync_count += 1;
+                if self.sync_count >= n {
+                    self.sync_count = 0;
+                    self.output = Some(self.frame());
+                }
+            }
+            TransmissionType::EventDriven => {}
+        }
+    }
+
+    fn event(&mut self) {
+        match self.transmission {
+            TransmissionType::SynchronousAcyclic => self.pending = true,
+            TransmissionType::SynchronousCyclic(_) => {}
+            TransmissionType::EventDriven => self.emit(),
+        }
+    }
+
+    fn tick(&mut self, ms: u32) {
+        if self.transmission != TransmissionType::EventDriven {
+            return;
+        }
+
+        let elapsed = ms.saturating_mul(10);
+        self.since_last = self.since_last.saturating_add(elapsed);
+        self.since_event = self.since_event.saturating_add(elapsed);
+
+        let timer_expired =
+            self.event_timer != 0 && self.since_event >= u32::from(self.event_timer) * 10;
+
+        if self.pending || timer_expired {
+            self.emit();
+        }
+    }
+
+    fn inhibited(&self) -> bool {
+        self.since_last < u32::from(self.inhibit)
+    }
+
+    /// Transmits an event-driven TPDO now, or holds it back during the inhibit time.
+    fn emit(&mut self) {
+        if self.inhibited() {
+            self.pending = true;
+            return;
+        }
+
+        self.pending = false;
+        self.since_last = 0;
+        self.since_event = 0;
+        self.output = Some(self.frame());
+    }
+}
+
+impl MachineTrans<TpdoInput> for Tpdo {
+    type Observation = Option<CANFrame>;
+
+    /// Processes a SYNC, an application event, the passing of time or a
+    /// change of the NMT state.
+    fn transit(&mut self, x: TpdoInput) {
+        self.output = None;
+
+        if let TpdoInput::State(state) = x {
+            self.on_state(state);
+            return;
+        }
+
+        if self.state != NmtState::Operational {
+            return;
+        }
+
+        match x {
+            TpdoInput::Sync => self.sync(),
+            TpdoInput::Event => self.event(),
+            TpdoInput::Tick(ms) => self.tick(ms),
+            TpdoInput::State(_) => {}
+        }
+    }
+
+    /// Returns the frame to transmit caused by the last input.
+    fn observe(&self) -> Self::Observation {
+        self.output
+    }
+
+    /// Restarts the SYNC count and the timers and forgets pending events,
+    /// keeping configuration and data. The node is assumed to be
+    /// initialising again.
+    fn initial(&mut self) {
+        self.state = NmtState::BootUp;
+        self.restart();
+        self.output = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdo_cobid() {
+        let tpdo3 = PdoId::tx(3, 10).unwrap();
+
+        assert_eq!(tpdo3.cobid(), 0x38a);
+        assert_eq!(PdoId::try_from(0x38a), Ok(tpdo3));
+
+        let rpdo1 = PdoId::rx(1, 0x7f).unwrap();
+
+        assert_eq!(rpdo1.cobid(), 0x27f);
+        assert_eq!(PdoId::try_from(0x27f), Ok(rpdo1));
+
+        for n in 1..=4 {
+            for node in [1, 64, 127] {
+                for id in [PdoId::tx(n, node).unwrap(), PdoId::rx(n, node).unwrap()] {
+                    assert_eq!(PdoId::try_from(id.cobid()), Ok(id));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pdo_cobid_invalid() {
+        assert_eq!(PdoId::tx(0, 10), Err(PdoError::InvalidPdoNumber(0)));
+        assert_eq!(PdoId::rx(5, 10), Err(PdoError::InvalidPdoNumber(5)));
+        assert_eq!(PdoId::tx(1, 0), Err(PdoError::InvalidNodeId(0)));
+        assert_eq!(PdoId::tx(1, 128), Err(PdoError::InvalidNodeId(128)));
+
+        // node 0 and non-PDO function codes
+        assert_eq!(PdoId::try_from(0x180), Err(PdoError::InvalidCobid(0x180)));
+        assert_eq!(PdoId::try_from(0x581), Err(PdoError::InvalidCobid(0x581)));
+        assert_eq!(PdoId::try_from(0x701), Err(PdoError::InvalidCobid(0x701)));
+    }
+
+    #[test]
+    fn test_transmission_type_encode_decode() {
+        assert_eq!(
+            TransmissionType::try_from(0),
+            Ok(TransmissionType::SynchronousAcyclic)
+        );
+        assert_eq!(
+            TransmissionType::try_from(240),
+            Ok(TransmissionType::SynchronousCyclic(240))
+        );
+        assert_eq!(
+            TransmissionType::try_from(254),
+            Ok(TransmissionType::EventDriven)
+        );
+        assert_eq!(u8::from(TransmissionType::SynchronousCyclic(3)), 3);
+
+        for x in [241, 251, 252, 253] {
+            assert_eq!(
+                TransmissionType::try_from(x),
+                Err(PdoError::InvalidTransmissionType(x))
+            );
+        }
+    }
+
+    fn operational(cobid: u32, transmission: TransmissionType) -> Tpdo {
+        let mut tpdo = Tpdo::new(cobid, transmission).unwrap();
+        tpdo.transit(TpdoInput::State(NmtState::Operational));
+        tpdo
+    }
+
+    #[test]
+    fn test_tpdo_synchronous_cyclic() {
+        let mut tpdo = operational(0x18a, TransmissionType::SynchronousCyclic(3));
+        tpdo.set_data(&[0x12, 0x34]).unwrap();
+
+        let mut sent = 0;
+        for i in 1..=9 {
+            tpdo.transit(TpdoInput::Sync);
+
+            match tpdo.observe() {
+                Some(frame) => {
+                    assert_eq!(i % 3, 0);
+                    assert_eq!(frame.can_cobid, 0x18a);
+                    assert_eq!(frame.can_len, 2);
+                    assert_eq!(&frame.can_data[..2], &[0x12, 0x34]);
+                    sent += 1;
+                }
+                None => assert_ne!(i % 3, 0),
+            }
+        }
+        assert_eq!(sent, 3);
+
+        // events do not trigger cyclic TPDOs
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+
+        for n in [0, 241] {
+            assert!(matches!(
+                Tpdo::new(0x18a, TransmissionType::SynchronousCyclic(n)),
+                Err(PdoError::InvalidTransmissionType(x)) if x == n
+            ));
+            assert_eq!(
+                tpdo.set_transmission(TransmissionType::SynchronousCyclic(n)),
+                Err(PdoError::InvalidTransmissionType(n))
+            );
+        }
+        assert_eq!(tpdo.transmission(), TransmissionType::SynchronousCyclic(3));
+    }
+
+    #[test]
+    fn test_tpdo_synchronous_acyclic() {
+        let mut tpdo = operational(0x18a, TransmissionType::SynchronousAcyclic);
+
+        tpdo.transit(TpdoInput::Sync);
+        assert_eq!(tpdo.observe(), None);
+
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::Event);
+
+        tpdo.transit(TpdoInput::Sync);
+        assert!(tpdo.observe().is_some());
+        tpdo.transit(TpdoInput::Sync);
+        assert_eq!(tpdo.observe(), None);
+    }
+
+    #[test]
+    fn test_tpdo_event_driven() {
+        let mut tpdo = operational(0x18a, TransmissionType::EventDriven);
+
+        tpdo.transit(TpdoInput::Sync);
+        assert_eq!(tpdo.observe(), None);
+
+        tpdo.transit(TpdoInput::Event);
+        assert!(tpdo.observe().is_some());
+
+        assert_eq!(tpdo.set_data(&[0; 9]), Err(PdoError::InvalidLength(9)));
+    }
+
+    #[test]
+    fn test_tpdo_event_timer() {
+        let mut tpdo = operational(0x18a, TransmissionType::EventDriven);
+        tpdo.set_event_timer(100);
+
+        let mut sent = 0;
+        for ms in 1..=500 {
+            tpdo.transit(TpdoInput::Tick(1));
+
+            if tpdo.observe().is_some() {
+                assert_eq!(ms % 100, 0);
+                sent += 1;
+            }
+        }
+        assert_eq!(sent, 5);
+
+        // an event restarts the event timer
+        tpdo.transit(TpdoInput::Tick(50));
+        tpdo.transit(TpdoInput::Event);
+        assert!(tpdo.observe().is_some());
+        tpdo.transit(TpdoInput::Tick(50));
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::Tick(50));
+        assert!(tpdo.observe().is_some());
+    }
+
+    #[test]
+    fn test_tpdo_inhibit_time() {
+        let mut tpdo = operational(0x18a, TransmissionType::EventDriven);
+        // 50ms inhibit time
+        tpdo.set_inhibit(500);
+
+        tpdo.transit(TpdoInput::Event);
+        assert!(tpdo.observe().is_some());
+
+        // a change 10ms later is held back
+        tpdo.transit(TpdoInput::Tick(10));
+        tpdo.set_data(&[0x01]).unwrap();
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+
+        tpdo.transit(TpdoInput::Tick(39));
+        assert_eq!(tpdo.observe(), None);
+
+        tpdo.transit(TpdoInput::Tick(1));
+        let frame = tpdo.observe().unwrap();
+        assert_eq!(frame.can_data[0], 0x01);
+
+        tpdo.transit(TpdoInput::Tick(100));
+        assert_eq!(tpdo.observe(), None);
+
+        // a 1.5ms inhibit time ends on the 2ms tick
+        tpdo.set_inhibit(15);
+        tpdo.transit(TpdoInput::Event);
+        assert!(tpdo.observe().is_some());
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::Tick(1));
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::Tick(1));
+        assert!(tpdo.observe().is_some());
+    }
+
+    #[test]
+    fn test_tpdo_requires_operational() {
+        let mut tpdo = Tpdo::new(0x18a, TransmissionType::EventDriven).unwrap();
+        tpdo.set_event_timer(10);
+
+        tpdo.transit(TpdoInput::State(NmtState::PreOperational));
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::Tick(20));
+        assert_eq!(tpdo.observe(), None);
+
+        tpdo.transit(TpdoInput::State(NmtState::Operational));
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::Event);
+        assert!(tpdo.observe().is_some());
+        tpdo.transit(TpdoInput::Tick(10));
+        assert!(tpdo.observe().is_some());
+
+        tpdo.transit(TpdoInput::State(NmtState::Stopped));
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+
+        // a reset returns the node to initialisation
+        tpdo.transit(TpdoInput::State(NmtState::Operational));
+        tpdo.initial();
+        tpdo.transit(TpdoInput::Event);
+        assert_eq!(tpdo.observe(), None);
+
+        let mut tpdo = Tpdo::new(0x18a, TransmissionType::SynchronousCyclic(1)).unwrap();
+
+        tpdo.transit(TpdoInput::Sync);
+        assert_eq!(tpdo.observe(), None);
+        tpdo.transit(TpdoInput::State(NmtState::Operational));
+        tpdo.transit(TpdoInput::Sync);
+        assert!(tpdo.observe().is_some());
+    }
+
+    #[test]
+    fn test_pdo_mapping_entry_layout() {
+        let entry = PdoMappingEntry::from(0x6000_0108);
+
+        assert_eq!(entry.index, 0x6000);
+        assert_eq!(entry.sub, 0x01);
+        assert_eq!(entry.bits, 8);
+        assert_eq!(u32::from(entry), 0x6000_0108);
+    }
+
+    #[test]
+    fn test_pdo_mapping_bits() {
+        let mut mapping: PdoMapping<9> = PdoMapping::default();
+
+        // a 4-bit field followed by eight digital inputs straddling the first byte
+        mapping.push(PdoMappingEntry::from(0x2000_0004)).unwrap();
+        for sub in 1..=8 {
+            mapping
+                .push(PdoMappingEntry {
+                    index: 0x6000,
+                    sub,
+                    bits: 1,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(mapping.bits(), 12);
+        assert_eq!(mapping.len_bytes(), 2);
+
+        let values = [0xa, 1, 0, 1, 1, 0, 0, 0, 1];
+        let mut data = [0; 8];
+
+        assert_eq!(mapping.pack(&values, &mut data), Ok(2));
+        assert_eq!(data, [0xda, 0x08, 0, 0, 0, 0, 0, 0]);
+
+        let mut decoded = [0; 9];
+        mapping.unpack(&data, &mut decoded).unwrap();
+
+        assert_eq!(decoded, values);
+
+        // bits above the object length are dropped
+        mapping
+            .pack(&[0xff, 0, 0, 0, 0, 0, 0, 0, 0], &mut data)
+            .unwrap();
+        assert_eq!(data[..2], [0x0f, 0x00]);
+    }
+
+    #[test]
+    fn test_pdo_mapping_limits() {
+        let mut mapping: PdoMapping<2> = PdoMapping::default();
+
+        assert_eq!(
+            mapping.push(PdoMappingEntry::from(0x6000_0100)),
+            Err(PdoError::InvalidMappingLength(0))
+        );
+
+        mapping.push(PdoMappingEntry::from(0x6000_0140)).unwrap();
+
+        let mut data = [0; 8];
+        mapping.pack(&[u64::MAX], &mut data).unwrap();
+        assert_eq!(data, [0xff; 8]);
+
+        assert_eq!(
+            mapping.push(PdoMappingEntry::from(0x6000_0201)),
+            Err(PdoError::MappingTooLong(65))
+        );
+
+        mapping.clear();
+        mapping.push(PdoMappingEntry::from(0x6000_0108)).unwrap();
+        mapping.push(PdoMappingEntry::from(0x6000_0208)).unwrap();
+
+        assert_eq!(
+            mapping.push(PdoMappingEntry::from(0x6000_0308)),
+            Err(PdoError::TooManyMappings)
+        );
+        assert_eq!(
+            mapping.pack(&[1], &mut data),
+            Err(PdoError::InvalidValueCount(1))
+        );
+    }
+}
@@ -3,22 +3,81 @@
 //! The `raw` module provides an abstract interface for working with raw CAN frames.
 
 use crate::machine::*;
+use core::fmt;
+
+/// Flag marking an extended (29-bit) identifier in a serialized COB-ID.
+///
+/// This matches the `CAN_EFF_FLAG` convention used by SocketCAN.
+pub const CAN_EFF_FLAG: u32 = 0x8000_0000;
+
+/// Flag marking a remote transmission request (RTR) in a serialized COB-ID.
+///
+/// This matches the `CAN_RTR_FLAG` convention used by SocketCAN.
+pub const CAN_RTR_FLAG: u32 = 0x4000_0000;
+
+/// Mask of a standard (11-bit) CAN identifier.
+pub const CAN_SFF_MASK: u32 = 0x0000_07ff;
+
+/// Mask of an extended (29-bit) CAN identifier.
+pub const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+
+/// Mask of the node ID in a COB-ID of the predefined connection set.
+pub const CAN_NODE_MASK: u32 = 0x0000_007f;
+
+/// Size of a serialized CAN frame.
+pub const CAN_MTU: usize = 16;
+
+/// Returns the 7-bit node ID of a COB-ID of the predefined connection set.
+pub fn node_of(cobid: u32) -> u8 {
+    (cobid & CAN_NODE_MASK) as u8
+}
+
+/// Returns `true` for the broadcast COB-IDs of the predefined connection set:
+/// NMT, SYNC and TIME.
+///
+/// Other COB-IDs with a zero node ID, e.g. 0x180, are not broadcasts, nor
+/// are the EMCY messages sharing the SYNC function code.
+pub fn is_broadcast(cobid: u32) -> bool {
+    matches!(
+        cobid,
+        crate::nmt::NMT_COBID | crate::sync::SYNC_COBID | crate::time::TIME_COBID
+    )
+}
 
 /// A structure representing RAW CAN frames.
 ///
 /// # Fields
 ///
 /// * `can_cobid` - The CAN identifier (COB-ID) of the frame. This is a 32-bit value that uniquely identifies the frame in the CAN network.
+/// * `is_extended` - Whether `can_cobid` is an extended 29-bit identifier rather than a standard 11-bit one.
+/// * `is_rtr` - Whether the frame is a remote transmission request.
 /// * `can_len` - The length of the CAN frame. Number of valid bytes in `can_data`
 /// * `can_data` - The data of the CAN frame. This is an array of 8 bytes containing the payload of the frame.
 ///
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "SerdeFrame<8>")
+)]
 pub struct CANFrame {
     /// The CAN identifier (COB-ID) of the frame.
     ///
     /// This is a 32-bit value that uniquely identifies the frame in the CAN network.
     pub can_cobid: u32,
 
+    /// Marks `can_cobid` as an extended 29-bit identifier.
+    ///
+    /// On the wire the flag is carried in the high bit of the stored COB-ID
+    /// (see [`CAN_EFF_FLAG`]).
+    pub is_extended: bool,
+
+    /// Marks the frame as a remote transmission request.
+    ///
+    /// RTR frames carry no payload; `can_len` holds the requested length.
+    /// On the wire the flag is carried in the stored COB-ID (see [`CAN_RTR_FLAG`]).
+    pub is_rtr: bool,
+
     /// The length of the CAN frame
     pub can_len: usize,
 
@@ -28,23 +87,264 @@ pub struct CANFrame {
     pub can_data: [u8; 8],
 }
 
-impl Default for CANFrame {
-    fn default() -> Self {
-        Self {
-            can_cobid: 0,
-            can_len: 0,
-            can_data: [0; 8],
+/// Compact serde representation of a frame, holding only the valid data bytes.
+///
+/// Remote frames carry their requested length in `len` and no data.
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct SerdeFrame<const N: usize> {
+    cobid: u32,
+    #[serde(default)]
+    extended: bool,
+    #[serde(default)]
+    rtr: bool,
+    len: usize,
+    data: SerdePayload<N>,
+}
+
+/// Serde representation of a CAN FD frame.
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+struct SerdeFdFrame {
+    cobid: u32,
+    #[serde(default)]
+    extended: bool,
+    len: usize,
+    #[serde(default)]
+    brs: bool,
+    #[serde(default)]
+    esi: bool,
+    data: SerdePayload<CANFD_MAX_DLEN>,
+}
+
+/// Up to `N` data bytes, serialized as a sequence of their actual length.
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy)]
+struct SerdePayload<const N: usize> {
+    len: usize,
+    data: [u8; N],
+}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> SerdePayload<N> {
+    fn new(data: &[u8]) -> Self {
+        let mut payload = Self {
+            len: data.len(),
+            data: [0; N],
+        };
+        payload.data[..data.len()].copy_from_slice(data);
+        payload
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for SerdePayload<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.data[..self.len])
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for SerdePayload<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<const N: usize>;
+
+        impl<'de, const N: usize> serde::de::Visitor<'de> for Visitor<N> {
+            type Value = SerdePayload<N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "at most {} data bytes", N)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut payload = SerdePayload {
+                    len: 0,
+                    data: [0; N],
+                };
+
+                while let Some(x) = seq.next_element()? {
+                    if payload.len == N {
+                        return Err(serde::de::Error::invalid_length(N + 1, &self));
+                    }
+                    payload.data[payload.len] = x;
+                    payload.len += 1;
+                }
+
+                Ok(payload)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CANFrame> for SerdeFrame<8> {
+    type Error = FrameError;
+
+    fn try_from(frame: CANFrame) -> Result<Self, Self::Error> {
+        encode_cobid(frame.can_cobid, frame.is_extended)?;
+        if frame.can_len > 8 {
+            return Err(FrameError::InvalidLength(frame.can_len));
+        }
+
+        let len = if frame.is_rtr { 0 } else { frame.can_len };
+
+        Ok(Self {
+            cobid: frame.can_cobid,
+            extended: frame.is_extended,
+            rtr: frame.is_rtr,
+            len: frame.can_len,
+            data: SerdePayload::new(&frame.can_data[..len]),
+        })
+    }
+}
+
+/// Serializes the frame compactly, failing for frames that could not be read back.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CANFrame {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerdeFrame::try_from(*self)
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeFrame<8>> for CANFrame {
+    type Error = FrameError;
+
+    fn try_from(frame: SerdeFrame<8>) -> Result<Self, Self::Error> {
+        encode_cobid(frame.cobid, frame.extended)?;
+        let expected = if frame.rtr { 0 } else { frame.len };
+        if frame.len > 8 || frame.data.len != expected {
+            return Err(FrameError::InvalidLength(frame.len));
+        }
+
+        Ok(Self {
+            can_cobid: frame.cobid,
+            is_extended: frame.extended,
+            is_rtr: frame.rtr,
+            can_len: frame.len,
+            can_data: frame.data.data,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CANFdFrame> for SerdeFdFrame {
+    type Error = FrameError;
+
+    fn try_from(frame: CANFdFrame) -> Result<Self, Self::Error> {
+        encode_cobid(frame.can_cobid, frame.is_extended)?;
+        if !is_canfd_len(frame.can_len) {
+            return Err(FrameError::InvalidLength(frame.can_len));
+        }
+
+        Ok(Self {
+            cobid: frame.can_cobid,
+            extended: frame.is_extended,
+            len: frame.can_len,
+            brs: frame.brs,
+            esi: frame.esi,
+            data: SerdePayload::new(&frame.can_data[..frame.can_len]),
+        })
+    }
+}
+
+/// Serializes the frame, failing for frames that could not be read back.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CANFdFrame {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerdeFdFrame::try_from(*self)
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeFdFrame> for CANFdFrame {
+    type Error = FrameError;
+
+    fn try_from(frame: SerdeFdFrame) -> Result<Self, Self::Error> {
+        encode_cobid(frame.cobid, frame.extended)?;
+        if !is_canfd_len(frame.len) || frame.data.len != frame.len {
+            return Err(FrameError::InvalidLength(frame.len));
         }
+
+        Ok(Self {
+            can_cobid: frame.cobid,
+            is_extended: frame.extended,
+            can_len: frame.len,
+            brs: frame.brs,
+            esi: frame.esi,
+            can_data: frame.data.data,
+        })
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for CANFrame {
+    /// Formats the COB-ID in hex and only the first `can_len` data bytes.
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "CANFrame {{ can_cobid: {=u32:#x}, is_extended: {=bool}, is_rtr: {=bool}, can_len: {=usize}, can_data: {=[u8]:#04x} }}",
+            self.can_cobid,
+            self.is_extended,
+            self.is_rtr,
+            self.can_len,
+            &self.can_data[..self.can_len.min(8)]
+        )
     }
 }
 
-impl CANFrame {   
-    /// Serializes raw CAN frame    
-    pub fn write_to_slice(self: &Self, buffer: &mut [u8]) {
-        assert!(buffer.len() >= 16, "Buffer must be at least 16 bytes long");
+impl CANFrame {
+    /// Returns a builder for a CAN frame.
+    pub fn builder() -> CANFrameBuilder {
+        CANFrameBuilder::default()
+    }
+
+    /// Serializes raw CAN frame
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is shorter than [`CAN_MTU`], the identifier is out of range
+    /// or the frame has more than 8 data bytes, see [`CANFrame::try_write_to_slice`].
+    pub fn write_to_slice(&self, buffer: &mut [u8]) {
+        self.try_write_to_slice(buffer)
+            .expect("Buffer must be at least 16 bytes long and the frame valid");
+    }
+
+    /// Deserializes raw CAN frame
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is shorter than [`CAN_MTU`] or announces more than 8 data bytes,
+    /// see [`CANFrame::try_read_from_slice`].
+    pub fn read_from_slice(buffer: &[u8]) -> Self {
+        Self::try_read_from_slice(buffer)
+            .expect("Buffer must hold a 16-byte frame with at most 8 data bytes")
+    }
+
+    /// Serializes raw CAN frame, returning the number of bytes written.
+    ///
+    /// Identifiers that do not fit into 11 bits, or 29 bits for extended
+    /// frames, are rejected with [`FrameError::InvalidCobid`], lengths above 8
+    /// with [`FrameError::InvalidLength`].
+    pub fn try_write_to_slice(&self, buffer: &mut [u8]) -> Result<usize, FrameError> {
+        check_buffer_len(buffer, CAN_MTU)?;
+        let raw = self.raw_cobid()?;
+
+        if self.can_len > 8 {
+            return Err(FrameError::InvalidLength(self.can_len));
+        }
 
         // Write COB-ID as little endian
-        buffer[0..4].copy_from_slice(&self.can_cobid.to_le_bytes());
+        buffer[0..4].copy_from_slice(&raw.to_le_bytes());
 
         // Write length
         buffer[4] = self.can_len as u8;
@@ -53,10 +353,166 @@ impl CANFrame {
         buffer[5..8].fill(0);
 
         // Write CAN data
-        buffer[8..16].copy_from_slice(&self.can_data);
+        buffer[8..CAN_MTU].copy_from_slice(&self.can_data);
+
+        Ok(CAN_MTU)
+    }
+
+    /// Deserializes raw CAN frame
+    ///
+    /// Like [`CANFrameMachine`], frames announcing more than 8 data bytes are
+    /// rejected with [`FrameError::InvalidLength`].
+    pub fn try_read_from_slice(buffer: &[u8]) -> Result<Self, FrameError> {
+        check_buffer_len(buffer, CAN_MTU)?;
+
+        let mut cobid = [0; 4];
+        cobid.copy_from_slice(&buffer[0..4]);
+
+        let mut frame = Self::default();
+        frame.set_raw_cobid(u32::from_le_bytes(cobid));
+        frame.can_len = buffer[4].into();
+
+        if frame.can_len > 8 {
+            return Err(FrameError::InvalidLength(frame.can_len));
+        }
+
+        // Only the first `can_len` data bytes are valid, the rest stays zero
+        frame.can_data[..frame.can_len].copy_from_slice(&buffer[8..8 + frame.can_len]);
+        Ok(frame)
+    }
+
+    /// Returns the COB-ID as stored on the wire, including the extended and RTR flags.
+    fn raw_cobid(&self) -> Result<u32, FrameError> {
+        let raw = encode_cobid(self.can_cobid, self.is_extended)?;
+        if self.is_rtr {
+            Ok(raw | CAN_RTR_FLAG)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    /// Splits a COB-ID as stored on the wire into the identifier and its flags.
+    fn set_raw_cobid(&mut self, raw: u32) {
+        (self.can_cobid, self.is_extended) = decode_cobid(raw);
+        self.is_rtr = raw & CAN_RTR_FLAG != 0;
+    }
+}
+
+/// A builder for [`CANFrame`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CANFrameBuilder {
+    frame: CANFrame,
+}
+
+impl CANFrameBuilder {
+    /// Sets the COB-ID of the frame.
+    pub fn cobid(mut self, cobid: u32) -> Self {
+        self.frame.can_cobid = cobid;
+        self
+    }
+
+    /// Marks the COB-ID as an extended 29-bit identifier.
+    pub fn extended(mut self, is_extended: bool) -> Self {
+        self.frame.is_extended = is_extended;
+        self
+    }
+
+    /// Marks the frame as a remote transmission request.
+    pub fn rtr(mut self, is_rtr: bool) -> Self {
+        self.frame.is_rtr = is_rtr;
+        self
+    }
+
+    /// Sets the payload of the frame.
+    ///
+    /// Copies up to 8 bytes, sets `can_len` accordingly and zero-fills the rest.
+    pub fn data(mut self, data: &[u8]) -> Self {
+        let len = data.len().min(8);
+        self.frame.can_data = [0; 8];
+        self.frame.can_data[..len].copy_from_slice(&data[..len]);
+        self.frame.can_len = len;
+        self
+    }
+
+    /// Builds the frame.
+    pub fn build(self) -> CANFrame {
+        self.frame
+    }
+}
+
+/// Checks that `buffer` can hold a serialized frame of `needed` bytes.
+fn check_buffer_len(buffer: &[u8], needed: usize) -> Result<(), FrameError> {
+    if buffer.len() < needed {
+        Err(FrameError::BufferTooSmall {
+            needed,
+            got: buffer.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Packs an identifier and its extended flag into the COB-ID stored on the wire.
+///
+/// Identifiers exceeding the 11-bit or 29-bit range are rejected rather than truncated.
+fn encode_cobid(cobid: u32, is_extended: bool) -> Result<u32, FrameError> {
+    if is_extended && cobid <= CAN_EFF_MASK {
+        Ok(cobid | CAN_EFF_FLAG)
+    } else if !is_extended && cobid <= CAN_SFF_MASK {
+        Ok(cobid)
+    } else {
+        Err(FrameError::InvalidCobid(cobid))
+    }
+}
+
+/// Splits a COB-ID stored on the wire into the identifier and its extended flag.
+fn decode_cobid(raw: u32) -> (u32, bool) {
+    if raw & CAN_EFF_FLAG != 0 {
+        (raw & CAN_EFF_MASK, true)
+    } else {
+        (raw & CAN_SFF_MASK, false)
+    }
+}
+
+/// Errors reported while processing raw CAN frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError {
+    /// The frame announces more data bytes than a classic CAN frame can carry.
+    InvalidLength(usize),
+
+    /// The identifier does not fit into 11 bits, or 29 bits for extended frames.
+    InvalidCobid(u32),
+
+    /// A remote frame cannot be carried as a CAN FD frame.
+    RemoteFrame,
+
+    /// The buffer is too small to hold a serialized frame.
+    BufferTooSmall {
+        /// Number of bytes required.
+        needed: usize,
+        /// Number of bytes available.
+        got: usize,
+    },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::InvalidLength(len) => write!(f, "invalid frame length {}", len),
+            FrameError::InvalidCobid(cobid) => {
+                write!(f, "CAN identifier out of range {:#x}", cobid)
+            }
+            FrameError::RemoteFrame => write!(f, "remote frames are not supported by CAN FD"),
+            FrameError::BufferTooSmall { needed, got } => {
+                write!(f, "buffer too small: needed {} bytes, got {}", needed, got)
+            }
+        }
     }
 }
 
+impl core::error::Error for FrameError {}
+
 /// Represents the possible states within a CAN frame processing sequence.
 enum State {
     Init,
@@ -73,11 +529,15 @@ enum State {
 }
 
 /// A state machine designed to process and construct raw CAN frames.
+///
+/// A frame announcing an invalid length is still consumed completely, so the
+/// machine stays aligned with the byte stream, and is then reported as an error.
 pub struct CANFrameMachine {
     state: State,
     can_frame: CANFrame,
     len: usize,
     index: usize,
+    error: Option<FrameError>,
 }
 
 impl Default for CANFrameMachine {
@@ -87,6 +547,7 @@ impl Default for CANFrameMachine {
             can_frame: CANFrame::default(),
             len: 0,
             index: 0,
+            error: None,
         }
     }
 }
@@ -96,33 +557,51 @@ impl CANFrameMachine {
     ///
     /// This method updates the state and manages the index where the byte is stored.
     /// Depending on the remaining length, it sets the next state appropriately.
-    fn get_data_byte(self: &mut Self, x: u8) {
+    fn get_data_byte(&mut self, x: u8) {
         if self.len > 1 {
-            self.len = self.len - 1;
+            self.len -= 1;
             self.state = State::Data;
             self.can_frame.can_data[self.index] = x;
         } else if self.len == 1 {
-            self.len = self.len - 1;
+            self.len -= 1;
             self.state = State::Final;
             self.can_frame.can_data[self.index] = x;
         } else {
             self.state = State::Final;
         }
 
-        self.index = self.index + 1;
+        self.index += 1;
+    }
+
+    /// Returns the observation and resets the machine once a frame has been consumed completely.
+    ///
+    /// Calling this after every input byte parses consecutive frames without a
+    /// manual [`MachineTrans::initial`]. Malformed frames are reported as errors
+    /// and reset the machine as well.
+    pub fn take_frame(&mut self) -> Result<Option<CANFrame>, FrameError> {
+        let observation = self.observe();
+
+        if !matches!(observation, Ok(None)) {
+            self.initial();
+        }
+
+        observation
     }
 }
 
 impl MachineTrans<u8> for CANFrameMachine {
-    type Observation = Option<CANFrame>;
+    type Observation = Result<Option<CANFrame>, FrameError>;
 
     /// Resets the machine's state and the CAN frame data to their initial conditions.
-    fn initial(self: &mut Self) {
+    fn initial(&mut self) {
         self.can_frame.can_cobid = 0;
+        self.can_frame.is_extended = false;
+        self.can_frame.is_rtr = false;
         self.can_frame.can_data.fill(0);
         self.can_frame.can_len = 0;
         self.len = 0;
         self.index = 0;
+        self.error = None;
         self.state = State::Init;
     }
 
@@ -130,7 +609,7 @@ impl MachineTrans<u8> for CANFrameMachine {
     ///
     /// Processes the input byte `x` and transitions the state machine to the next state
     /// as part of building a CAN frame.
-    fn transit(self: &mut Self, x: u8) {
+    fn transit(&mut self, x: u8) {
         match &self.state {
             State::Init => {
                 self.state = State::Id0;
@@ -139,23 +618,30 @@ impl MachineTrans<u8> for CANFrameMachine {
 
             State::Id0 => {
                 self.state = State::Id1;
-                self.can_frame.can_cobid = self.can_frame.can_cobid | ((x as u32) << 8);
+                self.can_frame.can_cobid |= (x as u32) << 8;
             }
 
             State::Id1 => {
                 self.state = State::Id2;
-                self.can_frame.can_cobid = self.can_frame.can_cobid | ((x as u32) << 16);
+                self.can_frame.can_cobid |= (x as u32) << 16;
             }
 
             State::Id2 => {
                 self.state = State::Id3;
-                self.can_frame.can_cobid = self.can_frame.can_cobid | ((x as u32) << 24);
+                let raw = self.can_frame.can_cobid | ((x as u32) << 24);
+                self.can_frame.set_raw_cobid(raw);
             }
 
             State::Id3 => {
                 self.state = State::Len;
                 let len: usize = x.into();
-                self.len = len;
+                if len > 8 {
+                    // skip the data bytes, the frame is reported as an error
+                    self.error = Some(FrameError::InvalidLength(len));
+                    self.len = 0;
+                } else {
+                    self.len = len;
+                }
                 self.can_frame.can_len = len;
             }
 
@@ -180,25 +666,29 @@ impl MachineTrans<u8> for CANFrameMachine {
             }
 
             State::Final => {
-                self.index = self.index + 1;
+                self.index += 1;
             }
         }
     }
 
     /// Observes the current machine state to check for a completed CAN frame.
     ///
-    /// Returns `Some(CANFrame)` if in a final state with a valid frame, otherwise `None`.
-    fn observe(self: &Self) -> Self::Observation {
+    /// Returns `Ok(Some(CANFrame))` if in a final state with a valid frame,
+    /// `Err(FrameError)` once a malformed frame has been consumed, otherwise `Ok(None)`.
+    fn observe(&self) -> Self::Observation {
         match self.state {
             State::Final => {
                 // should consume all input
                 if self.index == 8 {
-                    Some(self.can_frame)
+                    match self.error {
+                        Some(e) => Err(e),
+                        None => Ok(Some(self.can_frame)),
+                    }
                 } else {
-                    None
+                    Ok(None)
                 }
             }
-            _ => None,
+            _ => Ok(None),
         }
     }
 }
@@ -212,56 +702,1253 @@ impl Final for Option<CANFrame> {
     ///
     /// - `Some(CANFrame)` if the option contains a valid frame.
     /// - `None` if the option is empty.
-    fn is_final(self: Self) -> Option<Self::FinalValue> {
+    fn is_final(self) -> Option<Self::FinalValue> {
         self
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// An iterator that parses consecutive serialized CAN frames from a byte stream.
+///
+/// Every [`CAN_MTU`] bytes of input are decoded through a [`CANFrameMachine`].
+/// If the stream is misaligned, i.e. the next bytes do not look like a frame
+/// header (a standard COB-ID with stray high bits, a length above 8 or
+/// non-zero padding), the parser drops one byte at a time until it finds
+/// one. Resynchronisation is a heuristic: payload bytes that happen to look
+/// like a header can still be mistaken for one.
+pub struct FrameParser<I> {
+    input: I,
+    machine: CANFrameMachine,
+    window: [u8; CAN_MTU],
+    len: usize,
+}
 
-    #[test]
-    fn test_raw_can_frame_parsing() {
-        let frame = [
-            0x02, 0x07, 0x00, 0x00, // cobid
-            0x01, 0x00, 0x00, 0x00, // length with padding
-            0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // data
-        ];
+impl<I: Iterator<Item = u8>> FrameParser<I> {
+    /// Creates a parser reading bytes from `input`.
+    pub fn new(input: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            input: input.into_iter(),
+            machine: CANFrameMachine::default(),
+            window: [0; CAN_MTU],
+            len: 0,
+        }
+    }
+}
 
-        let mut parser = CANFrameMachine::default();
+impl<I: Iterator<Item = u8>> Iterator for FrameParser<I> {
+    type Item = CANFrame;
 
-        for x in frame {
-            parser.transit(x);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.len < CAN_MTU {
+                self.window[self.len] = self.input.next()?;
+                self.len += 1;
+            }
+
+            if !is_frame_header(&self.window) {
+                // misaligned, drop the first byte and try again
+                self.window.copy_within(1.., 0);
+                self.len -= 1;
+                continue;
+            }
+
+            self.len = 0;
+            self.machine.initial();
+            for x in self.window {
+                self.machine.transit(x);
+            }
+
+            if let Ok(Some(frame)) = self.machine.observe() {
+                return Some(frame);
+            }
         }
+    }
+}
 
-        let result = parser.observe().is_final().unwrap();
+/// Checks whether `window` starts with a plausible serialized frame header.
+fn is_frame_header(window: &[u8; CAN_MTU]) -> bool {
+    let raw = u32::from_le_bytes([window[0], window[1], window[2], window[3]]);
+    let id_ok = raw & CAN_EFF_FLAG != 0 || raw & CAN_EFF_MASK & !CAN_SFF_MASK == 0;
 
-        assert_eq!(result.can_cobid, 0x702);
-        assert_eq!(result.can_len, 1);
-        assert_eq!(result.can_data[0], 0x7f);
+    id_ok && window[4] <= 8 && window[5..8] == [0, 0, 0]
+}
+
+/// Bit rate switch flag of a CAN FD frame (SocketCAN's `CANFD_BRS`).
+pub const CANFD_BRS: u8 = 0x01;
+
+/// Error state indicator flag of a CAN FD frame (SocketCAN's `CANFD_ESI`).
+pub const CANFD_ESI: u8 = 0x02;
+
+/// Maximum payload length of a CAN FD frame.
+pub const CANFD_MAX_DLEN: usize = 64;
+
+/// Size of a serialized CAN FD frame.
+pub const CANFD_MTU: usize = 8 + CANFD_MAX_DLEN;
+
+/// Payload lengths addressed by the CAN FD DLC codes 9..15.
+const CANFD_DLC_LENGTHS: [usize; 7] = [12, 16, 20, 24, 32, 48, 64];
+
+/// Converts a CAN FD data length code into a payload length in bytes.
+///
+/// Codes 0..8 map linearly, codes 9..15 map to 12, 16, 20, 24, 32, 48 and 64.
+/// Bits above the 4-bit DLC field are ignored.
+pub fn dlc_to_len(dlc: u8) -> usize {
+    let dlc = dlc & 0x0f;
+    if dlc <= 8 {
+        dlc.into()
+    } else {
+        CANFD_DLC_LENGTHS[usize::from(dlc) - 9]
     }
+}
 
-    #[test]
-    fn test_raw_can_frame_decode_encode() {
-        let frame0: [u8; 16] = [
-            0x02, 0x07, 0x00, 0x00, // cobid
-            0x08, 0x00, 0x00, 0x00, // length with padding
-            0x7f, 0x7e, 0x7d, 0x7c, 0x00, 0x01, 0x02, 0x03, // data
-        ];
+/// Returns `true` if `len` is a payload length a CAN FD frame can carry exactly.
+pub fn is_canfd_len(len: usize) -> bool {
+    len <= 8 || CANFD_DLC_LENGTHS.contains(&len)
+}
 
-        let mut frame1: [u8; 16] = [0; 16];
-            
-        let mut parser = CANFrameMachine::default();
+/// Converts a payload length in bytes into the smallest CAN FD data length code that can carry it.
+///
+/// Lengths above [`CANFD_MAX_DLEN`] are clamped to DLC 15.
+pub fn len_to_dlc(len: usize) -> u8 {
+    if len <= 8 {
+        return len as u8;
+    }
 
-        for x in frame0 {
-            parser.transit(x);
+    match CANFD_DLC_LENGTHS.iter().position(|&l| len <= l) {
+        Some(i) => 9 + i as u8,
+        None => 15,
+    }
+}
+
+/// A structure representing RAW CAN FD frames.
+///
+/// # Fields
+///
+/// * `can_cobid` - The CAN identifier (COB-ID) of the frame.
+/// * `is_extended` - Whether `can_cobid` is an extended 29-bit identifier rather than a standard 11-bit one.
+/// * `can_len` - Number of valid bytes in `can_data`, up to 64.
+/// * `brs` - Bit rate switch: the data phase is transmitted at the higher bit rate.
+/// * `esi` - Error state indicator of the transmitting node.
+/// * `can_data` - The payload of the frame.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "SerdeFdFrame")
+)]
+pub struct CANFdFrame {
+    /// The CAN identifier (COB-ID) of the frame.
+    pub can_cobid: u32,
+
+    /// Marks `can_cobid` as an extended 29-bit identifier.
+    pub is_extended: bool,
+
+    /// The number of valid bytes in `can_data`.
+    pub can_len: usize,
+
+    /// Bit rate switch flag.
+    pub brs: bool,
+
+    /// Error state indicator flag.
+    pub esi: bool,
+
+    /// The data of the CAN FD frame.
+    pub can_data: [u8; CANFD_MAX_DLEN],
+}
+
+impl Default for CANFdFrame {
+    fn default() -> Self {
+        Self {
+            can_cobid: 0,
+            is_extended: false,
+            can_len: 0,
+            brs: false,
+            esi: false,
+            can_data: [0; CANFD_MAX_DLEN],
         }
+    }
+}
 
-        let can_frame = parser.observe().is_final().unwrap();
+#[cfg(feature = "defmt")]
+impl defmt::Format for CANFdFrame {
+    /// Formats the COB-ID in hex and only the first `can_len` data bytes.
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "CANFdFrame {{ can_cobid: {=u32:#x}, is_extended: {=bool}, can_len: {=usize}, brs: {=bool}, esi: {=bool}, can_data: {=[u8]:#04x} }}",
+            self.can_cobid,
+            self.is_extended,
+            self.can_len,
+            self.brs,
+            self.esi,
+            &self.can_data[..self.can_len.min(CANFD_MAX_DLEN)]
+        )
+    }
+}
 
-        can_frame.write_to_slice(&mut frame1);
+impl CANFdFrame {
+    /// Returns the data length code for the frame's payload length.
+    ///
+    /// Lengths that are not representable on the bus are rounded up to the next valid length.
+    pub fn can_dlc(&self) -> u8 {
+        len_to_dlc(self.can_len)
+    }
 
-        assert_eq!(frame0, frame1);
+    /// Serializes raw CAN FD frame
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is shorter than [`CANFD_MTU`], the identifier is out of range
+    /// or the length is not a valid CAN FD length, see [`CANFdFrame::try_write_to_slice`].
+    pub fn write_to_slice(&self, buffer: &mut [u8]) {
+        self.try_write_to_slice(buffer)
+            .expect("Buffer must be at least 72 bytes long and the frame valid");
+    }
+
+    /// Deserializes raw CAN FD frame
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is shorter than [`CANFD_MTU`] or announces an invalid length,
+    /// see [`CANFdFrame::try_read_from_slice`].
+    pub fn read_from_slice(buffer: &[u8]) -> Self {
+        Self::try_read_from_slice(buffer)
+            .expect("Buffer must hold a 72-byte frame with a valid CAN FD length")
+    }
+
+    /// Serializes raw CAN FD frame, returning the number of bytes written.
+    ///
+    /// Out of range identifiers are rejected like in [`CANFrame::try_write_to_slice`],
+    /// lengths that are not valid CAN FD lengths with [`FrameError::InvalidLength`].
+    pub fn try_write_to_slice(&self, buffer: &mut [u8]) -> Result<usize, FrameError> {
+        check_buffer_len(buffer, CANFD_MTU)?;
+        let raw = encode_cobid(self.can_cobid, self.is_extended)?;
+
+        if !is_canfd_len(self.can_len) {
+            return Err(FrameError::InvalidLength(self.can_len));
+        }
+
+        // Write COB-ID as little endian
+        buffer[0..4].copy_from_slice(&raw.to_le_bytes());
+
+        // Write length
+        buffer[4] = self.can_len as u8;
+
+        // Write flags
+        buffer[5] = self.flags();
+
+        // Fill 2 bytes with zero (reserved)
+        buffer[6..8].fill(0);
+
+        // Write CAN data
+        buffer[8..CANFD_MTU].copy_from_slice(&self.can_data);
+
+        Ok(CANFD_MTU)
+    }
+
+    /// Deserializes raw CAN FD frame
+    ///
+    /// Frames announcing a length that is not a valid CAN FD payload length
+    /// (see [`is_canfd_len`]) are rejected with [`FrameError::InvalidLength`].
+    pub fn try_read_from_slice(buffer: &[u8]) -> Result<Self, FrameError> {
+        check_buffer_len(buffer, CANFD_MTU)?;
+
+        let mut cobid = [0; 4];
+        cobid.copy_from_slice(&buffer[0..4]);
+
+        let mut frame = Self::default();
+        (frame.can_cobid, frame.is_extended) = decode_cobid(u32::from_le_bytes(cobid));
+        frame.can_len = buffer[4].into();
+        frame.brs = buffer[5] & CANFD_BRS != 0;
+        frame.esi = buffer[5] & CANFD_ESI != 0;
+
+        if !is_canfd_len(frame.can_len) {
+            return Err(FrameError::InvalidLength(frame.can_len));
+        }
+
+        // Only the first `can_len` data bytes are valid, the rest stays zero
+        frame.can_data[..frame.can_len].copy_from_slice(&buffer[8..8 + frame.can_len]);
+        Ok(frame)
+    }
+
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.brs {
+            flags |= CANFD_BRS;
+        }
+        if self.esi {
+            flags |= CANFD_ESI;
+        }
+        flags
+    }
+}
+
+impl TryFrom<CANFrame> for CANFdFrame {
+    type Error = FrameError;
+
+    /// Carries a classic data frame as a CAN FD frame without bit rate switching.
+    fn try_from(frame: CANFrame) -> Result<Self, Self::Error> {
+        if frame.is_rtr {
+            return Err(FrameError::RemoteFrame);
+        }
+
+        if frame.can_len > 8 {
+            return Err(FrameError::InvalidLength(frame.can_len));
+        }
+
+        let mut fd = Self {
+            can_cobid: frame.can_cobid,
+            is_extended: frame.is_extended,
+            can_len: frame.can_len,
+            ..Default::default()
+        };
+        fd.can_data[..8].copy_from_slice(&frame.can_data);
+        Ok(fd)
+    }
+}
+
+impl TryFrom<CANFdFrame> for CANFrame {
+    type Error = FrameError;
+
+    /// Converts a CAN FD frame back into a classic frame for the 8-byte CANOpen services.
+    ///
+    /// Frames with more than 8 data bytes are rejected.
+    fn try_from(frame: CANFdFrame) -> Result<Self, Self::Error> {
+        if frame.can_len > 8 {
+            return Err(FrameError::InvalidLength(frame.can_len));
+        }
+
+        let mut can_data = [0; 8];
+        can_data.copy_from_slice(&frame.can_data[..8]);
+
+        Ok(Self {
+            can_cobid: frame.can_cobid,
+            is_extended: frame.is_extended,
+            is_rtr: false,
+            can_len: frame.can_len,
+            can_data,
+        })
+    }
+}
+
+/// A state machine designed to process and construct raw CAN FD frames.
+///
+/// The machine collects the fixed 72-byte serialized layout produced by
+/// [`CANFdFrame::write_to_slice`] and yields the frame once it is complete,
+/// or an error if the frame announces an invalid length.
+pub struct CANFdFrameMachine {
+    buffer: [u8; CANFD_MTU],
+    index: usize,
+}
+
+impl Default for CANFdFrameMachine {
+    fn default() -> Self {
+        Self {
+            buffer: [0; CANFD_MTU],
+            index: 0,
+        }
+    }
+}
+
+impl MachineTrans<u8> for CANFdFrameMachine {
+    type Observation = Result<Option<CANFdFrame>, FrameError>;
+
+    /// Resets the machine to wait for the first byte of a frame.
+    fn initial(&mut self) {
+        self.buffer.fill(0);
+        self.index = 0;
+    }
+
+    /// Consumes an input byte of the serialized frame.
+    ///
+    /// Bytes arriving after the frame is complete are ignored until the machine is reset.
+    fn transit(&mut self, x: u8) {
+        if self.index < CANFD_MTU {
+            self.buffer[self.index] = x;
+            self.index += 1;
+        }
+    }
+
+    /// Returns `Ok(Some(CANFdFrame))` once all bytes of a valid frame have been consumed,
+    /// `Err(FrameError)` for a malformed frame, otherwise `Ok(None)`.
+    fn observe(&self) -> Self::Observation {
+        if self.index == CANFD_MTU {
+            CANFdFrame::try_read_from_slice(&self.buffer).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Final for Option<CANFdFrame> {
+    type FinalValue = CANFdFrame;
+
+    /// Determines if an `Option<CANFdFrame>` contains a final frame.
+    fn is_final(self) -> Option<Self::FinalValue> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_of() {
+        // NMT, SYNC and TIME
+        for cobid in [0x000, 0x080, 0x100] {
+            assert_eq!(node_of(cobid), 0);
+            assert!(is_broadcast(cobid));
+        }
+
+        for (cobid, node) in [(0x081, 0x01), (0x18a, 0x0a), (0x5ff, 0x7f), (0x705, 0x05)] {
+            assert_eq!(node_of(cobid), node);
+            assert!(!is_broadcast(cobid));
+        }
+
+        // a zero node ID alone does not make a broadcast
+        for cobid in [0x180, 0x200, 0x580, 0x700] {
+            assert_eq!(node_of(cobid), 0);
+            assert!(!is_broadcast(cobid));
+        }
+    }
+
+    #[test]
+    fn test_raw_can_frame_parsing() {
+        let frame = [
+            0x02, 0x07, 0x00, 0x00, // cobid
+            0x01, 0x00, 0x00, 0x00, // length with padding
+            0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // data
+        ];
+
+        let mut parser = CANFrameMachine::default();
+
+        for x in frame {
+            parser.transit(x);
+        }
+
+        let result = parser.observe().unwrap().unwrap();
+
+        assert_eq!(result.can_cobid, 0x702);
+        assert_eq!(result.can_len, 1);
+        assert_eq!(result.can_data[0], 0x7f);
+    }
+
+    #[test]
+    fn test_raw_can_frame_decode_encode() {
+        let frame0: [u8; 16] = [
+            0x02, 0x07, 0x00, 0x00, // cobid
+            0x08, 0x00, 0x00, 0x00, // length with padding
+            0x7f, 0x7e, 0x7d, 0x7c, 0x00, 0x01, 0x02, 0x03, // data
+        ];
+
+        let mut frame1: [u8; 16] = [0; 16];
+
+        let mut parser = CANFrameMachine::default();
+
+        for x in frame0 {
+            parser.transit(x);
+        }
+
+        let can_frame = parser.observe().unwrap().unwrap();
+
+        can_frame.write_to_slice(&mut frame1);
+
+        assert_eq!(frame0, frame1);
+    }
+
+    #[test]
+    fn test_raw_can_frame_extended_id() {
+        let frame0 = CANFrame {
+            can_cobid: 0x1abc_def0,
+            is_extended: true,
+            is_rtr: false,
+            can_len: 2,
+            can_data: [0x11, 0x22, 0, 0, 0, 0, 0, 0],
+        };
+
+        let mut buffer: [u8; 16] = [0; 16];
+        frame0.write_to_slice(&mut buffer);
+
+        assert_eq!(buffer[0..4], [0xf0, 0xde, 0xbc, 0x9a]);
+
+        let frame1 = CANFrame::read_from_slice(&buffer);
+
+        assert!(frame1.is_extended);
+        assert_eq!(frame1.can_cobid, 0x1abc_def0);
+        assert_eq!(frame1.can_len, 2);
+
+        let mut parser = CANFrameMachine::default();
+
+        for x in buffer {
+            parser.transit(x);
+        }
+
+        let frame2 = parser.observe().unwrap().unwrap();
+
+        assert!(frame2.is_extended);
+        assert_eq!(frame2.can_cobid, 0x1abc_def0);
+        assert_eq!(frame2.can_data[0..2], [0x11, 0x22]);
+    }
+
+    #[test]
+    fn test_raw_can_frame_standard_id() {
+        let buffer: [u8; 16] = [
+            0x02, 0x07, 0x00, 0x00, // cobid
+            0x00, 0x00, 0x00, 0x00, // length with padding
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // data
+        ];
+
+        let frame = CANFrame::read_from_slice(&buffer);
+
+        assert!(!frame.is_extended);
+        assert_eq!(frame.can_cobid, 0x702);
+    }
+
+    #[test]
+    fn test_canfd_dlc_mapping() {
+        let lengths = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+        for (dlc, len) in lengths.iter().enumerate() {
+            assert_eq!(dlc_to_len(dlc as u8), *len);
+            assert_eq!(len_to_dlc(*len), dlc as u8);
+        }
+
+        // lengths in between round up to the next code
+        assert_eq!(len_to_dlc(9), 9);
+        assert_eq!(len_to_dlc(13), 10);
+        assert_eq!(len_to_dlc(33), 14);
+        assert_eq!(len_to_dlc(49), 15);
+        assert_eq!(len_to_dlc(100), 15);
+    }
+
+    #[test]
+    fn test_canfd_frame_decode_encode() {
+        let mut frame0 = CANFdFrame {
+            can_cobid: 0x181,
+            can_len: 20,
+            brs: true,
+            ..Default::default()
+        };
+
+        for (i, x) in frame0.can_data[0..20].iter_mut().enumerate() {
+            *x = i as u8;
+        }
+
+        assert_eq!(frame0.can_dlc(), 11);
+
+        let mut buffer = [0; CANFD_MTU];
+        frame0.write_to_slice(&mut buffer);
+
+        assert_eq!(buffer[4], 20);
+        assert_eq!(buffer[5], CANFD_BRS);
+
+        let mut parser = CANFdFrameMachine::default();
+
+        for x in buffer {
+            parser.transit(x);
+        }
+
+        let frame1 = parser.observe().unwrap().unwrap();
+
+        assert_eq!(frame1.can_cobid, 0x181);
+        assert_eq!(frame1.can_len, 20);
+        assert!(frame1.brs);
+        assert!(!frame1.esi);
+        assert_eq!(frame1.can_data, frame0.can_data);
+    }
+
+    #[test]
+    fn test_canfd_frame_lengths() {
+        for len in [0, 8, 12, 48, 64] {
+            let mut frame0 = CANFdFrame {
+                can_cobid: 0x1234_5678,
+                is_extended: true,
+                can_len: len,
+                ..Default::default()
+            };
+
+            for (i, x) in frame0.can_data[..len].iter_mut().enumerate() {
+                *x = !(i as u8);
+            }
+
+            let mut buffer = [0xaa; CANFD_MTU];
+            assert_eq!(frame0.try_write_to_slice(&mut buffer), Ok(CANFD_MTU));
+
+            let mut parser = CANFdFrameMachine::default();
+
+            for x in buffer {
+                parser.transit(x);
+            }
+
+            assert_eq!(parser.observe(), Ok(Some(frame0)));
+            assert_eq!(usize::from(buffer[4]), len);
+            assert_eq!(dlc_to_len(frame0.can_dlc()), len);
+        }
+    }
+
+    #[test]
+    fn test_canfd_frame_invalid_length() {
+        let mut buffer = [0; CANFD_MTU];
+
+        for len in [9, 13, 63, 65, 255] {
+            buffer[4] = len;
+
+            assert_eq!(
+                CANFdFrame::try_read_from_slice(&buffer),
+                Err(FrameError::InvalidLength(len.into()))
+            );
+
+            let mut parser = CANFdFrameMachine::default();
+
+            for x in buffer {
+                parser.transit(x);
+            }
+
+            assert_eq!(parser.observe(), Err(FrameError::InvalidLength(len.into())));
+        }
+    }
+
+    #[test]
+    fn test_canfd_frame_classic_conversion() {
+        let classic = CANFrame::builder()
+            .cobid(0x581)
+            .data(&[0x43, 0x00, 0x10, 0x00, 0x92, 0x01, 0x00, 0x00])
+            .build();
+
+        let fd = CANFdFrame::try_from(classic).unwrap();
+
+        assert_eq!(fd.can_cobid, 0x581);
+        assert_eq!(fd.can_len, 8);
+        assert!(!fd.brs);
+        assert_eq!(CANFrame::try_from(fd), Ok(classic));
+
+        let long = CANFdFrame { can_len: 12, ..fd };
+
+        assert_eq!(CANFrame::try_from(long), Err(FrameError::InvalidLength(12)));
+
+        let remote = CANFrame::builder().cobid(0x701).rtr(true).build();
+
+        assert_eq!(CANFdFrame::try_from(remote), Err(FrameError::RemoteFrame));
+    }
+
+    #[test]
+    fn test_raw_can_frame_rtr() {
+        let frame0 = CANFrame {
+            can_cobid: 0x705,
+            is_rtr: true,
+            ..Default::default()
+        };
+
+        let mut buffer: [u8; 16] = [0; 16];
+        frame0.write_to_slice(&mut buffer);
+
+        assert_eq!(buffer[0..4], [0x05, 0x07, 0x00, 0x40]);
+        assert_eq!(buffer[4], 0);
+
+        let mut parser = CANFrameMachine::default();
+
+        for x in buffer {
+            parser.transit(x);
+        }
+
+        let frame1 = parser.observe().unwrap().unwrap();
+
+        assert!(frame1.is_rtr);
+        assert!(!frame1.is_extended);
+        assert_eq!(frame1.can_cobid, 0x705);
+        assert_eq!(frame1.can_len, 0);
+
+        let frame2 = CANFrame::read_from_slice(&buffer);
+
+        assert!(frame2.is_rtr);
+        assert_eq!(frame2.can_cobid, 0x705);
+    }
+
+    /// Counts the frames it receives.
+    #[derive(Default)]
+    struct Collector {
+        frames: usize,
+    }
+
+    impl MachineTrans<CANFrame> for Collector {
+        type Observation = usize;
+
+        fn transit(&mut self, _x: CANFrame) {
+            self.frames += 1;
+        }
+
+        fn observe(&self) -> Self::Observation {
+            self.frames
+        }
+
+        fn initial(&mut self) {
+            self.frames = 0;
+        }
+    }
+
+    #[test]
+    fn test_raw_can_frame_invalid_length() {
+        let frames: [u8; 48] = [
+            0x02, 0x07, 0x00, 0x00, // cobid
+            0x01, 0x00, 0x00, 0x00, // length with padding
+            0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // data
+            0x02, 0x07, 0x00, 0x00, // cobid
+            0x20, 0x00, 0x00, 0x00, // bogus length with padding
+            0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // data
+            0x02, 0x07, 0x00, 0x00, // cobid
+            0x01, 0x00, 0x00, 0x00, // length with padding
+            0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // data
+        ];
+
+        let mut machine = TryComp::new(CANFrameMachine::default(), Collector::default());
+        let mut errors = 0;
+
+        for x in frames {
+            machine.transit(x);
+            if let Err(e) = machine.observe() {
+                assert_eq!(e, FrameError::InvalidLength(0x20));
+                errors += 1;
+            }
+        }
+
+        assert_eq!(errors, 1);
+        assert_eq!(machine.observe(), Ok(2));
+    }
+
+    #[test]
+    fn test_raw_can_frame_write_invalid_length() {
+        let mut buffer = [0; CANFD_MTU];
+        let frame = CANFrame {
+            can_len: 9,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            frame.try_write_to_slice(&mut buffer),
+            Err(FrameError::InvalidLength(9))
+        );
+
+        for len in [13, 65] {
+            let frame = CANFdFrame {
+                can_len: len,
+                ..Default::default()
+            };
+
+            assert_eq!(
+                frame.try_write_to_slice(&mut buffer),
+                Err(FrameError::InvalidLength(len))
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_can_frame_cobid_out_of_range() {
+        let mut buffer: [u8; 16] = [0; 16];
+        let frame = CANFrame::builder().cobid(0x1234).build();
+
+        assert_eq!(
+            frame.try_write_to_slice(&mut buffer),
+            Err(FrameError::InvalidCobid(0x1234))
+        );
+
+        let frame = CANFrame::builder().cobid(0x1234).extended(true).build();
+
+        assert_eq!(frame.try_write_to_slice(&mut buffer), Ok(16));
+
+        let frame = CANFrame::builder()
+            .cobid(0x2000_0000)
+            .extended(true)
+            .build();
+
+        assert_eq!(
+            frame.try_write_to_slice(&mut buffer),
+            Err(FrameError::InvalidCobid(0x2000_0000))
+        );
+
+        let frame = CANFdFrame {
+            can_cobid: 0x800,
+            ..Default::default()
+        };
+        let mut buffer: [u8; 72] = [0; 72];
+
+        assert_eq!(
+            frame.try_write_to_slice(&mut buffer),
+            Err(FrameError::InvalidCobid(0x800))
+        );
+    }
+
+    #[test]
+    fn test_raw_can_frame_buffer_too_small() {
+        let frame = CANFrame::default();
+        let mut buffer: [u8; 15] = [0; 15];
+
+        assert_eq!(
+            frame.try_write_to_slice(&mut buffer),
+            Err(FrameError::BufferTooSmall {
+                needed: 16,
+                got: 15
+            })
+        );
+
+        assert_eq!(
+            CANFrame::try_read_from_slice(&buffer).err(),
+            Some(FrameError::BufferTooSmall {
+                needed: 16,
+                got: 15
+            })
+        );
+
+        let mut buffer: [u8; 72] = [0; 72];
+
+        assert_eq!(
+            CANFdFrame::default().try_write_to_slice(&mut buffer[..71]),
+            Err(FrameError::BufferTooSmall {
+                needed: 72,
+                got: 71
+            })
+        );
+        assert_eq!(
+            CANFdFrame::default().try_write_to_slice(&mut buffer),
+            Ok(72)
+        );
+    }
+
+    #[test]
+    fn test_raw_can_frame_exact_buffer() {
+        let frame0 = CANFrame {
+            can_cobid: 0x181,
+            can_len: 2,
+            can_data: [0x01, 0x02, 0, 0, 0, 0, 0, 0],
+            ..Default::default()
+        };
+
+        let mut buffer: [u8; 16] = [0; 16];
+
+        assert_eq!(frame0.try_write_to_slice(&mut buffer), Ok(16));
+
+        let frame1 = CANFrame::try_read_from_slice(&buffer).unwrap();
+
+        assert_eq!(frame1.can_cobid, 0x181);
+        assert_eq!(frame1.can_len, 2);
+        assert_eq!(frame1.can_data, frame0.can_data);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_raw_can_frame_write_panics_on_short_buffer() {
+        let mut buffer: [u8; 8] = [0; 8];
+        CANFrame::default().write_to_slice(&mut buffer);
+    }
+
+    #[test]
+    fn test_raw_can_frame_short_length() {
+        let frame: [u8; 16] = [
+            0x85, 0x01, 0x00, 0x00, // cobid
+            0x03, 0x00, 0x00, 0x00, // length with padding
+            0x01, 0x02, 0x03, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, // data with trailing garbage
+        ];
+
+        let mut parser = CANFrameMachine::default();
+
+        for (i, x) in frame.iter().enumerate() {
+            parser.transit(*x);
+            if i < 15 {
+                assert_eq!(parser.observe(), Ok(None));
+            }
+        }
+
+        let result = parser.observe().unwrap().unwrap();
+
+        assert_eq!(result.can_cobid, 0x185);
+        assert_eq!(result.can_len, 3);
+        assert_eq!(result.can_data, [0x01, 0x02, 0x03, 0, 0, 0, 0, 0]);
+
+        let result = CANFrame::read_from_slice(&frame);
+
+        assert_eq!(result.can_len, 3);
+        assert_eq!(result.can_data, [0x01, 0x02, 0x03, 0, 0, 0, 0, 0]);
+    }
+
+    fn serialize(frames: &[CANFrame], buffer: &mut [u8]) {
+        for (frame, chunk) in frames.iter().zip(buffer.chunks_mut(CAN_MTU)) {
+            frame.write_to_slice(chunk);
+        }
+    }
+
+    fn sample_frames() -> [CANFrame; 3] {
+        [
+            CANFrame {
+                can_cobid: 0x701,
+                can_len: 1,
+                can_data: [0x05, 0, 0, 0, 0, 0, 0, 0],
+                ..Default::default()
+            },
+            CANFrame {
+                can_cobid: 0x581,
+                can_len: 8,
+                can_data: [0x43, 0x00, 0x10, 0x00, 0x92, 0x01, 0x02, 0x00],
+                ..Default::default()
+            },
+            CANFrame {
+                can_cobid: 0x080,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_frame_parser() {
+        let frames = sample_frames();
+        let mut stream: [u8; 48] = [0; 48];
+        serialize(&frames, &mut stream);
+
+        let mut parser = FrameParser::new(stream);
+
+        for frame in frames {
+            assert_eq!(parser.next(), Some(frame));
+        }
+
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn test_frame_parser_resync() {
+        let frames = sample_frames();
+        let mut stream: [u8; 51] = [0; 51];
+        stream[0..3].fill(0xff);
+        serialize(&frames, &mut stream[3..]);
+
+        let parsed = FrameParser::new(stream);
+
+        assert!(parsed.eq(frames));
+    }
+
+    #[test]
+    fn test_raw_can_frame_machine_take_frame() {
+        let frames = sample_frames();
+        let mut stream: [u8; 48] = [0; 48];
+        serialize(&frames, &mut stream);
+        // the second frame announces 9 data bytes
+        stream[CAN_MTU + 4] = 9;
+
+        let mut machine = CANFrameMachine::default();
+        let mut taken = [None; 3];
+        let mut n = 0;
+
+        for x in stream {
+            machine.transit(x);
+
+            match machine.take_frame() {
+                Ok(None) => continue,
+                result => taken[n] = Some(result),
+            }
+            n += 1;
+
+            // the machine is back in its initial state
+            assert_eq!(machine.observe(), Ok(None));
+        }
+
+        assert_eq!(n, 3);
+        assert_eq!(taken[0], Some(Ok(Some(frames[0]))));
+        assert_eq!(taken[1], Some(Err(FrameError::InvalidLength(9))));
+        assert_eq!(taken[2], Some(Ok(Some(frames[2]))));
+    }
+
+    #[test]
+    fn test_raw_can_frame_builder() {
+        let frame = CANFrame::builder().cobid(0x201).data(&[1, 2, 3]).build();
+
+        assert_eq!(frame.can_cobid, 0x201);
+        assert_eq!(frame.can_len, 3);
+        assert_eq!(frame.can_data, [1, 2, 3, 0, 0, 0, 0, 0]);
+        assert!(!frame.is_extended);
+        assert!(!frame.is_rtr);
+
+        let frame = CANFrame::builder()
+            .cobid(0x1234_5678)
+            .extended(true)
+            .data(&[0; 10])
+            .build();
+
+        assert!(frame.is_extended);
+        assert_eq!(frame.can_len, 8);
+
+        let frame = CANFrame::builder().cobid(0x705).rtr(true).build();
+
+        assert!(frame.is_rtr);
+        assert_eq!(frame.can_len, 0);
+    }
+
+    /// Sums the first data byte of pairs of frames.
+    #[derive(Default)]
+    struct PairDecoder {
+        frames: usize,
+        sum: u8,
+    }
+
+    struct PairSum(Option<u8>);
+
+    impl Final for PairSum {
+        type FinalValue = u8;
+
+        fn is_final(self) -> Option<u8> {
+            self.0
+        }
+    }
+
+    impl MachineTrans<CANFrame> for PairDecoder {
+        type Observation = PairSum;
+
+        fn transit(&mut self, x: CANFrame) {
+            self.frames += 1;
+            self.sum += x.can_data[0];
+        }
+
+        fn observe(&self) -> Self::Observation {
+            if self.frames == 2 {
+                PairSum(Some(self.sum))
+            } else {
+                PairSum(None)
+            }
+        }
+
+        fn initial(&mut self) {
+            self.frames = 0;
+            self.sum = 0;
+        }
+    }
+
+    /// Parses frames from a stream known to be valid, panicking on errors.
+    #[derive(Default)]
+    struct ValidFrames(CANFrameMachine);
+
+    impl MachineTrans<u8> for ValidFrames {
+        type Observation = Option<CANFrame>;
+
+        fn transit(&mut self, x: u8) {
+            self.0.transit(x);
+        }
+
+        fn observe(&self) -> Self::Observation {
+            self.0.observe().unwrap()
+        }
+
+        fn initial(&mut self) {
+            self.0.initial();
+        }
+    }
+
+    /// Remembers the last value it receives.
+    #[derive(Default)]
+    struct Sink {
+        last: Option<u8>,
+    }
+
+    impl MachineTrans<u8> for Sink {
+        type Observation = Option<u8>;
+
+        fn transit(&mut self, x: u8) {
+            self.last = Some(x);
+        }
+
+        fn observe(&self) -> Self::Observation {
+            self.last
+        }
+
+        fn initial(&mut self) {
+            self.last = None;
+        }
+    }
+
+    #[test]
+    fn test_comp3() {
+        let mut stream: [u8; 64] = [0; 64];
+        let frames = [1, 2, 10, 20].map(|x| CANFrame::builder().cobid(0x181).data(&[x]).build());
+        serialize(&frames, &mut stream);
+
+        let mut machine = Comp3 {
+            m0: ValidFrames::default(),
+            m1: PairDecoder::default(),
+            m2: Sink::default(),
+        };
+
+        for x in &stream[..32] {
+            machine.transit(*x);
+        }
+
+        assert_eq!(machine.observe(), Some(3));
+
+        for x in &stream[32..] {
+            machine.transit(*x);
+        }
+
+        assert_eq!(machine.observe(), Some(30));
+    }
+
+    /// Deterministic pseudo-random bytes (xorshift32).
+    fn noise(seed: u32, len: usize) -> impl Iterator<Item = u8> {
+        let mut x = seed;
+        (0..len).map(move |_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        })
+    }
+
+    #[test]
+    fn test_raw_can_frame_machine_arbitrary_input() {
+        for seed in 1..=64 {
+            let mut machine = CANFrameMachine::default();
+
+            for x in noise(seed, 4096) {
+                machine.transit(x);
+
+                match machine.observe() {
+                    Ok(Some(frame)) => {
+                        assert!(frame.can_len <= 8);
+                        machine.initial();
+                    }
+                    Ok(None) => {}
+                    Err(FrameError::InvalidLength(len)) => {
+                        assert!(len > 8);
+                        machine.initial();
+                    }
+                    Err(e) => panic!("unexpected error {:?}", e),
+                }
+            }
+
+            // every frame yielded by the parser has a valid length
+            assert!(FrameParser::new(noise(seed, 4096)).all(|f| f.can_len <= 8));
+        }
+    }
+
+    #[test]
+    fn test_raw_can_frame_read_arbitrary_input() {
+        let mut buffer = [0; CANFD_MTU];
+
+        for seed in 1..=256 {
+            for (b, x) in buffer.iter_mut().zip(noise(seed, CANFD_MTU)) {
+                *b = x;
+            }
+
+            match CANFrame::try_read_from_slice(&buffer) {
+                Ok(frame) => assert!(frame.can_len <= 8),
+                Err(e) => assert!(matches!(e, FrameError::InvalidLength(len) if len > 8)),
+            }
+
+            match CANFdFrame::try_read_from_slice(&buffer) {
+                Ok(frame) => assert!(is_canfd_len(frame.can_len)),
+                Err(e) => {
+                    assert!(matches!(e, FrameError::InvalidLength(len) if !is_canfd_len(len)))
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "defmt")]
+    #[test]
+    fn test_raw_can_frame_defmt() {
+        fn assert_format<T: defmt::Format>() {}
+
+        assert_format::<CANFrame>();
+        assert_format::<CANFdFrame>();
+        assert_format::<FrameError>();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_raw_can_frame_serde() {
+        let frame0 = CANFrame::builder()
+            .cobid(0x181)
+            .data(&[0x01, 0x02, 0x03])
+            .build();
+
+        let json = serde_json::to_string(&frame0).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"cobid":385,"extended":false,"rtr":false,"len":3,"data":[1,2,3]}"#
+        );
+        assert_eq!(serde_json::from_str::<CANFrame>(&json).unwrap(), frame0);
+
+        let remote = CANFrame::builder().cobid(0x701).rtr(true).build();
+        let json = serde_json::to_string(&remote).unwrap();
+
+        assert_eq!(serde_json::from_str::<CANFrame>(&json).unwrap(), remote);
+
+        // flags default to false
+        let frame1: CANFrame =
+            serde_json::from_str(r#"{"cobid":385,"len":3,"data":[1,2,3]}"#).unwrap();
+
+        assert_eq!(frame1, frame0);
+
+        for json in [
+            r#"{"cobid":385,"len":2,"data":[1,2,3]}"#,
+            r#"{"cobid":385,"len":9,"data":[1,2,3,4,5,6,7,8,9]}"#,
+            r#"{"cobid":2048,"len":0,"data":[]}"#,
+            r#"{"cobid":536870912,"extended":true,"len":0,"data":[]}"#,
+        ] {
+            assert!(serde_json::from_str::<CANFrame>(json).is_err());
+        }
+
+        // frames that could not be read back are not serialized
+        let mut frame2 = frame0;
+        frame2.can_len = 9;
+
+        assert!(serde_json::to_string(&frame2).is_err());
+
+        let mut frame3 = frame0;
+        frame3.can_cobid = 0x800;
+
+        assert!(serde_json::to_string(&frame3).is_err());
+
+        frame3.is_extended = true;
+
+        assert!(serde_json::to_string(&frame3).is_ok());
+
+        frame3.can_cobid = CAN_EFF_MASK + 1;
+
+        assert!(serde_json::to_string(&frame3).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_canfd_frame_serde() {
+        let mut frame0 = CANFdFrame {
+            can_cobid: 0x181,
+            can_len: 48,
+            brs: true,
+            ..Default::default()
+        };
+        frame0.can_data[..48].fill(0x55);
+
+        let json = serde_json::to_string(&frame0).unwrap();
+
+        assert_eq!(serde_json::from_str::<CANFdFrame>(&json).unwrap(), frame0);
+
+        for len in [13, 65] {
+            let frame1 = CANFdFrame {
+                can_len: len,
+                ..Default::default()
+            };
+
+            assert!(serde_json::to_string(&frame1).is_err());
+        }
+
+        let frame2 = CANFdFrame {
+            can_cobid: 0x800,
+            ..Default::default()
+        };
+
+        assert!(serde_json::to_string(&frame2).is_err());
+
+        for json in [
+            r#"{"cobid":385,"len":13,"data":[0,0,0,0,0,0,0,0,0,0,0,0,0]}"#,
+            r#"{"cobid":2048,"len":0,"data":[]}"#,
+        ] {
+            assert!(serde_json::from_str::<CANFdFrame>(json).is_err());
+        }
     }
 }
@@ -0,0 +1,261 @@
+//! # Sync Module
+//!
+//! The `sync` module provides the CANOpen SYNC message and a producer
+//! broadcasting it with an optional counter.
+
+use crate::raw::*;
+use core::fmt;
+
+/// COB-ID of the SYNC message.
+pub const SYNC_COBID: u32 = 0x080;
+
+/// Largest synchronous counter overflow value (object 0x1019).
+pub const SYNC_OVERFLOW_MAX: u8 = 240;
+
+/// Errors reported while processing SYNC messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SyncError {
+    /// The counter overflow value is above 240.
+    InvalidOverflow(u8),
+    /// The frame was not sent on the SYNC COB-ID.
+    InvalidCobid(u32),
+    /// The frame carries neither an empty nor a 1-byte payload.
+    InvalidLength(usize),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::InvalidOverflow(x) => write!(f, "invalid SYNC counter overflow {}", x),
+            SyncError::InvalidCobid(cobid) => write!(f, "invalid SYNC COB-ID {:#x}", cobid),
+            SyncError::InvalidLength(len) => write!(f, "invalid SYNC frame length {}", len),
+        }
+    }
+}
+
+impl core::error::Error for SyncError {}
+
+/// A SYNC message, carrying a counter if the producer is configured with
+/// a counter overflow value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SyncMessage {
+    /// Counter value, 1 up to the counter overflow value.
+    pub counter: Option<u8>,
+}
+
+impl SyncMessage {
+    /// Builds the SYNC frame, with a 1-byte payload if a counter is present.
+    pub fn to_frame(&self) -> CANFrame {
+        let mut frame = CANFrame {
+            can_cobid: SYNC_COBID,
+            ..Default::default()
+        };
+
+        if let Some(counter) = self.counter {
+            frame.can_len = 1;
+            frame.can_data[0] = counter;
+        }
+
+        frame
+    }
+}
+
+impl TryFrom<&CANFrame> for SyncMessage {
+    type Error = SyncError;
+
+    /// Decodes a received SYNC frame, checking its COB-ID and length.
+    fn try_from(frame: &CANFrame) -> Result<Self, Self::Error> {
+        if frame.can_cobid != SYNC_COBID || frame.is_extended {
+            return Err(SyncError::InvalidCobid(frame.can_cobid));
+        }
+
+        match frame.can_len {
+            0 => Ok(Self { counter: None }),
+            1 => Ok(Self {
+                counter: Some(frame.can_data[0]),
+            }),
+            len => Err(SyncError::InvalidLength(len)),
+        }
+    }
+}
+
+/// Producer of the SYNC message.
+///
+/// A SYNC is sent every communication cycle period (object 0x1006, in
+/// microseconds), a period of zero disables the producer. With a non-zero
+/// synchronous counter overflow value (object 0x1019) every SYNC carries a
+/// counter counting from 1 up to the overflow value and wrapping back to 1;
+/// with zero the SYNC has no payload.
+///
+/// Time is ticked in milliseconds like the other machines. A period that is
+/// not a whole number of milliseconds is kept on average, the remainder
+/// carrying over to the next SYNC.
+pub struct SyncProducer {
+    period: u32,
+    overflow: u8,
+    counter: u8,
+    elapsed: u32,
+}
+
+impl SyncProducer {
+    /// Creates a producer sending a SYNC without counter every `period` microseconds.
+    pub fn new(period: u32) -> Self {
+        Self {
+            period,
+            overflow: 0,
+            counter: 1,
+            elapsed: 0,
+        }
+    }
+
+    /// Changes the communication cycle period, restarting it.
+    pub fn set_period(&mut self, period: u32) {
+        self.period = period;
+        self.elapsed = 0;
+    }
+
+    /// Returns the synchronous counter overflow value.
+    pub fn overflow(&self) -> u8 {
+        self.overflow
+    }
+
+    /// Changes the synchronous counter overflow value, restarting the counter at 1.
+    ///
+    /// Zero sends SYNCs without counter, values above 240 are rejected.
+    pub fn set_overflow(&mut self, overflow: u8) -> Result<(), SyncError> {
+        if overflow > SYNC_OVERFLOW_MAX {
+            return Err(SyncError::InvalidOverflow(overflow));
+        }
+
+        self.overflow = overflow;
+        self.counter = 1;
+        Ok(())
+    }
+
+    /// Advances time by `ms` milliseconds and returns the SYNC to send, if any.
+    pub fn tick(&mut self, ms: u32) -> Option<CANFrame> {
+        if self.period == 0 {
+            return None;
+        }
+
+        self.elapsed = self.elapsed.saturating_add(ms.saturating_mul(1000));
+        if self.elapsed < self.period {
+            return None;
+        }
+
+        self.elapsed %= self.period;
+        Some(self.next().to_frame())
+    }
+
+    fn next(&mut self) -> SyncMessage {
+        if self.overflow == 0 {
+            return SyncMessage { counter: None };
+        }
+
+        let counter = self.counter;
+        self.counter = if counter >= self.overflow {
+            1
+        } else {
+            counter + 1
+        };
+
+        SyncMessage {
+            counter: Some(counter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_message_encode_decode() {
+        let frame = SyncMessage { counter: Some(7) }.to_frame();
+
+        assert_eq!(frame.can_cobid, SYNC_COBID);
+        assert_eq!(frame.can_len, 1);
+        assert_eq!(
+            SyncMessage::try_from(&frame),
+            Ok(SyncMessage { counter: Some(7) })
+        );
+
+        let frame = SyncMessage::default().to_frame();
+
+        assert_eq!(frame.can_len, 0);
+        assert_eq!(
+            SyncMessage::try_from(&frame),
+            Ok(SyncMessage { counter: None })
+        );
+
+        let mut frame = SyncMessage::default().to_frame();
+        frame.can_len = 2;
+
+        assert_eq!(
+            SyncMessage::try_from(&frame),
+            Err(SyncError::InvalidLength(2))
+        );
+
+        frame.can_cobid = 0x081;
+
+        assert_eq!(
+            SyncMessage::try_from(&frame),
+            Err(SyncError::InvalidCobid(0x081))
+        );
+    }
+
+    #[test]
+    fn test_sync_producer_counter_overflow() {
+        // 10ms period
+        let mut sync = SyncProducer::new(10_000);
+        sync.set_overflow(240).unwrap();
+
+        for i in 1..=240 {
+            assert_eq!(sync.tick(9), None);
+            let frame = sync.tick(1).unwrap();
+
+            assert_eq!(frame.can_len, 1);
+            assert_eq!(frame.can_data[0], i);
+        }
+
+        // wraps from the overflow value back to 1
+        let frame = sync.tick(10).unwrap();
+
+        assert_eq!(frame.can_data[0], 1);
+        assert_eq!(sync.set_overflow(241), Err(SyncError::InvalidOverflow(241)));
+        assert_eq!(sync.overflow(), 240);
+    }
+
+    #[test]
+    fn test_sync_producer_without_counter() {
+        let mut sync = SyncProducer::new(1000);
+
+        for _ in 0..3 {
+            let frame = sync.tick(1).unwrap();
+
+            assert_eq!(frame.can_cobid, SYNC_COBID);
+            assert_eq!(frame.can_len, 0);
+        }
+
+        // a counter restarts at 1 after changing the overflow value
+        sync.set_overflow(2).unwrap();
+
+        let counters = [(); 4].map(|_| sync.tick(1).unwrap().can_data[0]);
+
+        assert_eq!(counters, [1, 2, 1, 2]);
+
+        // a 1.5ms period sends 2 SYNCs every 3ms
+        sync.set_period(1500);
+
+        let sent = (0..30).filter(|_| sync.tick(1).is_some()).count();
+
+        assert_eq!(sent, 20);
+
+        sync.set_period(0);
+
+        assert_eq!(sync.tick(1000), None);
+    }
+}
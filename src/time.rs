@@ -0,0 +1,255 @@
+//! # Time Module
+//!
+//! The `time` module provides the CANOpen TIME protocol: the TIME_OF_DAY
+//! value broadcast by a time producer on COB-ID 0x100.
+
+use crate::raw::*;
+use core::fmt;
+
+/// COB-ID of the TIME protocol.
+pub const TIME_COBID: u32 = 0x100;
+
+/// Number of valid bytes in a TIME frame.
+pub const TIME_LEN: usize = 6;
+
+/// Milliseconds in a day.
+pub const MILLIS_PER_DAY: u32 = 86_400_000;
+
+/// Milliseconds between the Unix epoch and 1984-01-01, the CANOpen epoch.
+pub const UNIX_EPOCH_OFFSET_MS: u64 = 5113 * MILLIS_PER_DAY as u64;
+
+/// Mask of the 28-bit millisecond field.
+const MILLIS_MASK: u32 = 0x0fff_ffff;
+
+/// Errors reported while processing TIME values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimeError {
+    /// The milliseconds after midnight exceed one day.
+    MillisOutOfRange(u32),
+    /// The number of days since 1984 does not fit into 16 bits.
+    DaysOutOfRange(u64),
+    /// The Unix time in milliseconds lies before 1984.
+    BeforeEpoch(u64),
+    /// The frame was not sent on the TIME COB-ID.
+    InvalidCobid(u32),
+    /// The frame does not carry a 6-byte payload.
+    InvalidLength(usize),
+}
+
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeError::MillisOutOfRange(ms) => {
+                write!(f, "milliseconds after midnight out of range: {}", ms)
+            }
+            TimeError::DaysOutOfRange(days) => write!(f, "days since 1984 out of range: {}", days),
+            TimeError::BeforeEpoch(ms) => write!(f, "Unix time {} ms lies before 1984", ms),
+            TimeError::InvalidCobid(cobid) => write!(f, "invalid TIME COB-ID {:#x}", cobid),
+            TimeError::InvalidLength(len) => write!(f, "invalid TIME frame length {}", len),
+        }
+    }
+}
+
+impl core::error::Error for TimeError {}
+
+/// The CANOpen TIME_OF_DAY value.
+///
+/// # Fields
+///
+/// * `millis_after_midnight` - Milliseconds after midnight, carried in a 28-bit field.
+/// * `days_since_1984` - Days since January 1, 1984.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    /// Milliseconds after midnight.
+    pub millis_after_midnight: u32,
+
+    /// Days since January 1, 1984.
+    pub days_since_1984: u16,
+}
+
+impl TimeOfDay {
+    /// Creates a time of day, checking that `millis_after_midnight` is within a day.
+    pub fn new(millis_after_midnight: u32, days_since_1984: u16) -> Result<Self, TimeError> {
+        if millis_after_midnight >= MILLIS_PER_DAY {
+            return Err(TimeError::MillisOutOfRange(millis_after_midnight));
+        }
+
+        Ok(Self {
+            millis_after_midnight,
+            days_since_1984,
+        })
+    }
+
+    /// Converts milliseconds since 1984-01-01 into a time of day.
+    pub fn from_millis(ms: u64) -> Result<Self, TimeError> {
+        let days = ms / u64::from(MILLIS_PER_DAY);
+        let days_since_1984 = u16::try_from(days).map_err(|_| TimeError::DaysOutOfRange(days))?;
+
+        Ok(Self {
+            millis_after_midnight: (ms % u64::from(MILLIS_PER_DAY)) as u32,
+            days_since_1984,
+        })
+    }
+
+    /// Returns the milliseconds since 1984-01-01.
+    pub fn as_millis(&self) -> u64 {
+        u64::from(self.days_since_1984) * u64::from(MILLIS_PER_DAY)
+            + u64::from(self.millis_after_midnight)
+    }
+
+    /// Converts milliseconds since the Unix epoch into a time of day.
+    ///
+    /// Times before 1984 cannot be represented and are rejected.
+    pub fn from_unix_millis(ms: u64) -> Result<Self, TimeError> {
+        let ms = ms
+            .checked_sub(UNIX_EPOCH_OFFSET_MS)
+            .ok_or(TimeError::BeforeEpoch(ms))?;
+        Self::from_millis(ms)
+    }
+
+    /// Returns the milliseconds since the Unix epoch.
+    pub fn as_unix_millis(&self) -> u64 {
+        self.as_millis() + UNIX_EPOCH_OFFSET_MS
+    }
+
+    /// Builds the TIME frame broadcasting this time of day.
+    pub fn to_frame(&self) -> CANFrame {
+        CANFrame {
+            can_cobid: TIME_COBID,
+            can_len: TIME_LEN,
+            can_data: (*self).into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<TimeOfDay> for [u8; 8] {
+    /// Encodes the 6-byte TIME_OF_DAY payload.
+    ///
+    /// Only the low 28 bits of `millis_after_midnight` are transmitted, the
+    /// reserved top nibble and the two unused bytes are zero.
+    fn from(t: TimeOfDay) -> [u8; 8] {
+        let mut data = [0; 8];
+        data[0..4].copy_from_slice(&(t.millis_after_midnight & MILLIS_MASK).to_le_bytes());
+        data[4..6].copy_from_slice(&t.days_since_1984.to_le_bytes());
+        data
+    }
+}
+
+impl TryFrom<[u8; 8]> for TimeOfDay {
+    type Error = TimeError;
+
+    /// Decodes the 6-byte TIME_OF_DAY payload, ignoring the reserved top nibble.
+    fn try_from(data: [u8; 8]) -> Result<Self, Self::Error> {
+        let ms = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) & MILLIS_MASK;
+        let days = u16::from_le_bytes([data[4], data[5]]);
+
+        Self::new(ms, days)
+    }
+}
+
+impl TryFrom<&CANFrame> for TimeOfDay {
+    type Error = TimeError;
+
+    /// Decodes a received TIME frame, checking its COB-ID and length.
+    fn try_from(frame: &CANFrame) -> Result<Self, Self::Error> {
+        if frame.can_cobid != TIME_COBID || frame.is_extended {
+            return Err(TimeError::InvalidCobid(frame.can_cobid));
+        }
+
+        if frame.can_len != TIME_LEN {
+            return Err(TimeError::InvalidLength(frame.can_len));
+        }
+
+        Self::try_from(frame.can_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_of_day_encode_decode() {
+        // 12:34:56.789 on 2024-01-01, 14610 days after 1984-01-01
+        let t = TimeOfDay::new(45_296_789, 14610).unwrap();
+        let data: [u8; 8] = t.into();
+
+        assert_eq!(data, [0x95, 0x2c, 0xb3, 0x02, 0x12, 0x39, 0x00, 0x00]);
+        assert_eq!(TimeOfDay::try_from(data), Ok(t));
+        assert_eq!(t.as_unix_millis(), 1_704_112_496_789);
+        assert_eq!(TimeOfDay::from_unix_millis(1_704_112_496_789), Ok(t));
+
+        let frame = t.to_frame();
+
+        assert_eq!(frame.can_cobid, 0x100);
+        assert_eq!(frame.can_len, 6);
+        assert_eq!(frame.can_data, data);
+    }
+
+    #[test]
+    fn test_time_of_day_reserved_bits() {
+        let data = [0x00, 0x00, 0x00, 0xf0, 0x01, 0x00, 0x00, 0x00];
+
+        assert_eq!(TimeOfDay::try_from(data), Ok(TimeOfDay::new(0, 1).unwrap()));
+    }
+
+    #[test]
+    fn test_time_of_day_millis_overflow() {
+        // fits into 28 bits but exceeds one day
+        let data = [0xff, 0xff, 0xff, 0x0f, 0x00, 0x00, 0x00, 0x00];
+
+        assert_eq!(
+            TimeOfDay::try_from(data),
+            Err(TimeError::MillisOutOfRange(0x0fff_ffff))
+        );
+        assert_eq!(
+            TimeOfDay::new(MILLIS_PER_DAY, 0),
+            Err(TimeError::MillisOutOfRange(MILLIS_PER_DAY))
+        );
+        assert_eq!(
+            TimeOfDay::from_millis(0x1_0000 * u64::from(MILLIS_PER_DAY)),
+            Err(TimeError::DaysOutOfRange(0x1_0000))
+        );
+    }
+
+    #[test]
+    fn test_time_of_day_before_epoch() {
+        assert_eq!(
+            TimeOfDay::from_unix_millis(UNIX_EPOCH_OFFSET_MS),
+            Ok(TimeOfDay::default())
+        );
+        assert_eq!(
+            TimeOfDay::from_unix_millis(UNIX_EPOCH_OFFSET_MS - 1),
+            Err(TimeError::BeforeEpoch(UNIX_EPOCH_OFFSET_MS - 1))
+        );
+        assert_eq!(
+            TimeOfDay::from_unix_millis(0),
+            Err(TimeError::BeforeEpoch(0))
+        );
+    }
+
+    #[test]
+    fn test_time_of_day_from_frame() {
+        let t = TimeOfDay::new(1000, 2).unwrap();
+        let mut frame = t.to_frame();
+
+        assert_eq!(TimeOfDay::try_from(&frame), Ok(t));
+
+        frame.can_len = 8;
+        assert_eq!(
+            TimeOfDay::try_from(&frame),
+            Err(TimeError::InvalidLength(8))
+        );
+
+        frame.can_cobid = 0x101;
+        assert_eq!(
+            TimeOfDay::try_from(&frame),
+            Err(TimeError::InvalidCobid(0x101))
+        );
+    }
+}